@@ -53,7 +53,7 @@ fn main() -> anyhow::Result<()> {
       .add_plugins(FrameTimeDiagnosticsPlugin{
         ..default()
       })
-      .add_plugins(RandomPlugin)
+      .add_plugins(RandomPlugin::new())
       .add_plugins(GameStatePlugin::new(
         GamePhase::MainMenu,
         GamePhase::WorldBuilding,
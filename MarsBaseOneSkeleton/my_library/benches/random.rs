@@ -10,5 +10,90 @@ pub fn criterion_benchmark(c: &mut Criterion) {
   });
 }
 
-criterion_group!(benches, criterion_benchmark);
+// Compares every algorithm compiled into this build. Enable the `pcg`
+// and `xorshift` features together to include all of them.
+pub fn algorithm_benchmark(c: &mut Criterion) {
+  let mut group = c.benchmark_group("algorithm");
+  for algorithm in Algorithm::ALL {
+    group.bench_function(algorithm.name(), |b| {
+      let rng = &mut RandomNumberGenerator::with_algorithm(*algorithm, 1);
+      b.iter(|| {
+        rng.range(1.0_f32..10_000_000_f32);
+      })
+    });
+  }
+  group.finish();
+}
+
+// Multi-threaded throughput of the `locking` generator, compared with
+// the previous design: one generator behind a single `Mutex`.
+// Run with `cargo bench --features locking`.
+#[cfg(feature = "locking")]
+mod threaded {
+  use criterion::{BenchmarkId, Criterion, Throughput};
+  use my_library_mbone_skeleton::{Algorithm, RandomNumberGenerator};
+  use rand::{Rng, SeedableRng};
+  use std::sync::Mutex;
+
+  // Both sides use ChaCha12 (`StdRng`), so only the locking differs.
+  type RngCore = rand_chacha::ChaCha12Rng;
+
+  const DRAWS_PER_THREAD: u64 = 100_000;
+
+  struct MutexRng(Mutex<RngCore>);
+
+  impl MutexRng {
+    fn range(&self, range: std::ops::Range<f32>) -> f32 {
+      self.0.lock().unwrap().gen_range(range)
+    }
+  }
+
+  fn run_threads(threads: u64, draw: impl Fn() + Sync) {
+    std::thread::scope(|scope| {
+      for _ in 0..threads {
+        scope.spawn(|| (0..DRAWS_PER_THREAD).for_each(|_| draw()));
+      }
+    });
+  }
+
+  pub fn threaded_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("threaded");
+    for threads in [1, 2, 4, 8] {
+      group.throughput(Throughput::Elements(threads * DRAWS_PER_THREAD));
+
+      let rng = MutexRng(Mutex::new(RngCore::seed_from_u64(1)));
+      group.bench_with_input(
+        BenchmarkId::new("mutex", threads),
+        &threads,
+        |b, threads| {
+          b.iter(|| run_threads(*threads, || {
+            rng.range(1.0_f32..10_000_000_f32);
+          }))
+        },
+      );
+
+      let rng = RandomNumberGenerator::with_algorithm(Algorithm::StdRng, 1);
+      group.bench_with_input(
+        BenchmarkId::new("sharded", threads),
+        &threads,
+        |b, threads| {
+          b.iter(|| run_threads(*threads, || {
+            rng.range(1.0_f32..10_000_000_f32);
+          }))
+        },
+      );
+    }
+    group.finish();
+  }
+}
+
+#[cfg(feature = "locking")]
+criterion_group!(
+  benches,
+  criterion_benchmark,
+  algorithm_benchmark,
+  threaded::threaded_benchmark
+);
+#[cfg(not(feature = "locking"))]
+criterion_group!(benches, criterion_benchmark, algorithm_benchmark);
 criterion_main!(benches);
@@ -1,15 +1,16 @@
 //! Roll 3d6 repeatedly and graph the resulting distribution.
-use my_library_mbone_skeleton::RandomNumberGenerator;
+use my_library_mbone_skeleton::{DiceExpression, RandomNumberGenerator};
 
-fn main() {
+fn main() -> anyhow::Result<()> {
   // Create a random number generator
   let mut rng = RandomNumberGenerator::new();
+  // Parse the dice expression once, outside of the loop
+  let three_d_six = DiceExpression::parse("3d6")?;
   // Store the results (minus 3)
   let mut results = vec![0; 16];
   // Roll 1,000 sets of 3d6 and increment results to map distribution
   for _ in 0..1_000 {
-    let roll: usize =
-      rng.range(1..=6) + rng.range(1..=6) + rng.range(1..=6);
+    let roll = rng.roll(&three_d_six).total as usize;
     results[roll - 3] += 1;
   }
   // Print the distribution histogram
@@ -21,4 +22,5 @@ fn main() {
     }
     println!();
   }
+  Ok(())
 }
@@ -0,0 +1,31 @@
+//! Run statistical quality tests on every random number generation
+//! algorithm in this build, and print a pass/fail report.
+//!
+//! Enable the `pcg` and `xorshift` features together to test all of
+//! them: `cargo run --example random_quality --features xorshift`
+use my_library_mbone_skeleton::{
+  quality_tests, Algorithm, RandomNumberGenerator,
+};
+
+const SAMPLES: usize = 1_000_000;
+
+fn main() -> anyhow::Result<()> {
+  let mut failures = 0;
+  for algorithm in Algorithm::ALL {
+    println!("{algorithm} ({SAMPLES} samples per test):");
+    // Use the same seed for every algorithm, so runs are comparable
+    let mut rng = RandomNumberGenerator::with_algorithm(*algorithm, 1);
+    for test in quality_tests(&mut rng, SAMPLES) {
+      println!("  {test}");
+      if !test.passed {
+        failures += 1;
+      }
+    }
+    println!();
+  }
+  if failures > 0 {
+    anyhow::bail!("{failures} test(s) failed");
+  }
+  println!("All tests passed");
+  Ok(())
+}
@@ -0,0 +1,198 @@
+// Asset groups are loaded when the game enters a state that uses them,
+// and released when it leaves for a state that doesn't. Assets outside
+// any group are loaded at startup and kept for the whole session.
+use crate::{AssetStore, AssetType, AssetsToLoad};
+use crate::bevy_assets::load_asset;
+use bevy::{log, prelude::*};
+use bevy::state::state::FreelyMutableState;
+
+#[derive(Clone)]
+pub(crate) struct AssetGroup {
+  pub(crate) name: String,
+  pub(crate) assets: Vec<(String, String, AssetType)>,
+  pub(crate) loaded: bool,
+}
+
+// Which groups each state uses, from `GameStatePlugin::with_asset_group`.
+#[derive(Resource)]
+pub(crate) struct AssetGroupStates<T> {
+  pub(crate) groups: Vec<(T, String)>,
+  // The state to enter once the loading menu has loaded its groups
+  pub(crate) resume: Option<T>,
+}
+
+impl<T: States> AssetGroupStates<T> {
+  fn groups_for<'a>(
+    &'a self,
+    state: &'a T,
+  ) -> impl Iterator<Item = &'a str> {
+    self
+      .groups
+      .iter()
+      .filter(move |(group_state, _)| group_state == state)
+      .map(|(_, group)| group.as_str())
+  }
+}
+
+impl AssetStore {
+  // Starts loading the group called `name`, returning the tags to wait
+  // for. Groups that are already loaded return nothing.
+  pub(crate) fn load_group(
+    &mut self,
+    name: &str,
+    asset_server: &AssetServer,
+  ) -> Vec<String> {
+    let Some(index) = self.groups.iter().position(|g| g.name == name) else {
+      log::error!("Unknown asset group {name}");
+      return Vec::new();
+    };
+    if self.groups[index].loaded {
+      return Vec::new();
+    }
+    self.groups[index].loaded = true;
+    let assets = self.groups[index].assets.clone();
+    let before: Vec<String> = self.asset_index.keys().cloned().collect();
+    for (tag, filename, asset_type) in assets.iter() {
+      load_asset(self, asset_server, tag, filename, asset_type);
+    }
+    self
+      .asset_index
+      .keys()
+      .filter(|tag| !before.contains(tag))
+      .cloned()
+      .collect()
+  }
+
+  // Drops the store's handles to a group's assets. Bevy unloads each
+  // asset once nothing else (such as a sprite) holds a handle to it.
+  pub(crate) fn release_group(&mut self, name: &str) {
+    let Some(group) = self.groups.iter_mut().find(|g| g.name == name)
+    else {
+      return;
+    };
+    if !group.loaded {
+      return;
+    }
+    group.loaded = false;
+    for (tag, _, _) in group.assets.iter() {
+      self.asset_index.remove(tag);
+      self.asset_index.remove(&format!("{tag}_base"));
+      self.atlases.remove(tag);
+      self.atlases_to_build.retain(|atlas| atlas.tag != *tag);
+      self.fonts.retain(|font| font != tag);
+    }
+    log::info!("Released asset group {name}");
+  }
+}
+
+// Runs before state transitions. When the game is about to enter a state
+// whose groups aren't loaded, they start loading and the game goes to
+// the loading menu instead, which enters the state when they are done.
+pub(crate) fn load_groups<T>(
+  mut next_state: ResMut<NextState<T>>,
+  state: Res<State<T>>,
+  mut group_states: ResMut<AssetGroupStates<T>>,
+  store: Option<ResMut<AssetStore>>,
+  asset_server: Res<AssetServer>,
+  mut commands: Commands,
+) where
+  T: States + FreelyMutableState + Default,
+{
+  let NextState::Pending(entering) = next_state.as_ref() else {
+    return;
+  };
+  let entering = entering.clone();
+  // The store doesn't exist until startup loading has begun
+  let Some(mut store) = store else {
+    return;
+  };
+  if entering == T::default() {
+    return;
+  }
+  let pending: Vec<String> = group_states
+    .groups_for(&entering)
+    .flat_map(|group| store.load_group(group, &asset_server))
+    .collect();
+  if pending.is_empty() {
+    return;
+  }
+  group_states.resume = Some(entering);
+  commands.insert_resource(AssetsToLoad::new(pending));
+  if *state.get() == T::default() {
+    next_state.reset();
+  } else {
+    next_state.set(T::default());
+  }
+}
+
+// Runs on leaving a state that uses groups, releasing the groups that the
+// next state doesn't use.
+pub(crate) fn release_groups<T>(
+  state: Res<State<T>>,
+  group_states: Res<AssetGroupStates<T>>,
+  mut store: ResMut<AssetStore>,
+) where
+  T: States,
+{
+  // On the way to the loading menu, keep what the state after it uses
+  let entering = group_states.resume.as_ref().unwrap_or(state.get());
+  let keep: Vec<&str> = group_states.groups_for(entering).collect();
+  let release: Vec<String> = store
+    .groups
+    .iter()
+    .filter(|group| group.loaded && !keep.contains(&group.name.as_str()))
+    .map(|group| group.name.clone())
+    .collect();
+  for group in release {
+    store.release_group(&group);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::FutureAtlas;
+  use bevy::{asset::LoadedUntypedAsset, platform::collections::HashMap};
+
+  #[test]
+  fn test_release_group() {
+    let sheet = AssetType::SpriteSheet {
+      tile_size: Vec2::splat(8.0),
+      sprites_x: 2,
+      sprites_y: 1,
+      padding: Vec2::ZERO,
+      offset: Vec2::ZERO,
+    };
+    let handle = Handle::<LoadedUntypedAsset>::default;
+    let mut store = AssetStore {
+      asset_index: HashMap::from_iter([
+        ("ship".to_string(), handle()),
+        ("boss".to_string(), handle()),
+        ("flames_base".to_string(), handle()),
+      ]),
+      atlases_to_build: Vec::from_iter(FutureAtlas::new("flames", &sheet)),
+      atlases: HashMap::from_iter([(
+        "flames".to_string(),
+        (Handle::default(), Handle::default()),
+      )]),
+      fonts: Vec::new(),
+      groups: vec![AssetGroup {
+        name: "level_1".to_string(),
+        assets: vec![
+          ("boss".to_string(), "boss.png".to_string(), AssetType::Image),
+          ("flames".to_string(), "flames.png".to_string(), sheet),
+        ],
+        loaded: true,
+      }],
+      requests: Vec::new(),
+    };
+
+    store.release_group("level_1");
+    assert!(!store.groups[0].loaded);
+    let mut tags: Vec<&String> = store.asset_index.keys().collect();
+    tags.sort();
+    assert_eq!(tags, ["ship"]);
+    assert!(store.atlases.is_empty());
+    assert!(store.atlases_to_build.is_empty());
+  }
+}
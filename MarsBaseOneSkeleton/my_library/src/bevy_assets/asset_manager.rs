@@ -1,24 +1,87 @@
-use crate::{AssetStore, FutureAtlas};
+use crate::{AssetGroup, AssetStore, AtlasDescriptor, FutureAtlas};
+use crate::ManifestFormat;
+use crate::bevy_assets::parse_manifest;
+#[cfg(feature = "embedded_assets")]
+use crate::bevy_assets::embedded_file;
 use bevy::prelude::*;
 
 //START: AssetType
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AssetType {
   Image,
   Sound,
   //START_HIGHLIGHT
-  SpriteSheet{tile_size: Vec2, sprites_x: usize, sprites_y: usize},
+  SpriteSheet{tile_size: Vec2, sprites_x: usize, sprites_y: usize,
+    padding: Vec2, offset: Vec2},
   //END_HIGHLIGHT
+  PackedSpriteSheet(AtlasDescriptor),
+  Font,
 }
 //END: AssetType
 
 //START: asset_manager_struct
 #[derive(Resource, Clone)]// <callout id="asset_manager_resource" />
 pub struct AssetManager {
-  asset_list: Vec<(String, String, AssetType)>, // <callout id="asset_list_tuple" />
+  pub(crate) asset_list: Vec<(String, String, AssetType)>, // <callout id="asset_list_tuple" />
+  // Manifest files the list was read from, watched by `hot_reload`
+  pub(crate) manifests: Vec<String>,
+  pub(crate) groups: Vec<AssetGroup>,
 }
 //END: asset_manager_struct
 
+/// How the sprites in a grid sprite sheet are laid out, for
+/// [`AssetManager::add_sprite_sheet_with_layout`].
+///
+/// ```
+/// use bevy::prelude::*;
+/// use my_library_mbone_skeleton::SpriteSheetLayout;
+///
+/// // 4x2 sprites of 32x32 pixels, 2 pixels apart, after a 1 pixel border
+/// let layout = SpriteSheetLayout::new(32.0, 32.0, 4, 2)
+///   .with_padding(Vec2::splat(2.0))
+///   .with_offset(Vec2::splat(1.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteSheetLayout {
+  tile_size: Vec2,
+  sprites_x: usize,
+  sprites_y: usize,
+  padding: Vec2,
+  offset: Vec2,
+}
+
+impl SpriteSheetLayout {
+  /// `sprites_x` by `sprites_y` sprites, packed together from the
+  /// image's top-left corner.
+  pub fn new(
+    sprite_width: f32,
+    sprite_height: f32,
+    sprites_x: usize,
+    sprites_y: usize,
+  ) -> Self {
+    Self {
+      tile_size: Vec2::new(sprite_width, sprite_height),
+      sprites_x,
+      sprites_y,
+      padding: Vec2::ZERO,
+      offset: Vec2::ZERO,
+    }
+  }
+
+  /// Leaves `padding` pixels between sprites.
+  pub fn with_padding(mut self, padding: Vec2) -> Self {
+    self.padding = padding;
+    self
+  }
+
+  /// Starts the first sprite `offset` pixels from the image's top-left
+  /// corner.
+  pub fn with_offset(mut self, offset: Vec2) -> Self {
+    self.offset = offset;
+    self
+  }
+}
+
 //START: asset_manager_new
 impl AssetManager {
   pub fn new() -> Self {
@@ -31,28 +94,152 @@ impl AssetManager {
          AssetType::Image),
       ],
       //END_HIGHLIGHT
+      manifests: Vec::new(),
+      groups: Vec::new(),
     }
   }
   //END: asset_manager_new
 
+  /// Adds a named group of assets that is only loaded while the game is
+  /// in a state that uses it (see
+  /// [`GameStatePlugin::with_asset_group`](crate::GameStatePlugin::with_asset_group)).
+  /// The group is built by `assets`, with the same methods as the rest
+  /// of the manager. Assets outside any group are loaded at startup and
+  /// kept for the whole session.
+  ///
+  /// ```no_run
+  /// # use my_library_mbone_skeleton::AssetManager;
+  /// # fn main() -> anyhow::Result<()> {
+  /// let assets = AssetManager::new()
+  ///   .add_image("ship", "ship.png")?
+  ///   .add_group("level_1", |group| group
+  ///     .add_image("boss", "boss.png")?
+  ///     .add_sound("roar", "roar.ogg"))?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn add_group<S: ToString>(
+    mut self,
+    name: S,
+    assets: impl FnOnce(AssetManager) -> anyhow::Result<AssetManager>,
+  ) -> anyhow::Result<Self> {
+    let name = name.to_string();
+    if self.groups.iter().any(|group| group.name == name) {
+      return Err(anyhow::Error::msg(format!(
+        "Asset group {name} is added more than once"
+      )));
+    }
+    let group = assets(AssetManager {
+      asset_list: Vec::new(),
+      manifests: Vec::new(),
+      groups: Vec::new(),
+    })?;
+    if !group.groups.is_empty() {
+      return Err(anyhow::Error::msg(format!(
+        "Asset group {name} contains another group"
+      )));
+    }
+    // Releasing a group removes its tags, so they can't be shared
+    let taken: Vec<&str> = self
+      .asset_list
+      .iter()
+      .chain(self.groups.iter().flat_map(|group| group.assets.iter()))
+      .map(|(tag, _, _)| tag.as_str())
+      .collect();
+    let shared: Vec<&str> = group
+      .asset_list
+      .iter()
+      .map(|(tag, _, _)| tag.as_str())
+      .filter(|tag| taken.contains(tag))
+      .collect();
+    if !shared.is_empty() {
+      return Err(anyhow::Error::msg(format!(
+        "Asset group {name} reuses the tags {}",
+        shared.join(", ")
+      )));
+    }
+    self.groups.push(AssetGroup {
+      name,
+      assets: group.asset_list,
+      loaded: false,
+    });
+    Ok(self)
+  }
+
   //START: check_exists
   fn asset_exists(filename: &str) -> anyhow::Result<()> {
-    #[cfg(not(target_arch = "wasm32"))]
+    AssetManager::assets_exist([filename])
+  }
+
+  // Checks a list of files at once, so that an error lists every
+  // missing file rather than just the first.
+  pub(crate) fn assets_exist<'a>(
+    filenames: impl IntoIterator<Item = &'a str>,
+  ) -> anyhow::Result<()> {
+    #[cfg(feature = "embedded_assets")]
+    {
+      let missing: Vec<&str> = filenames
+        .into_iter()
+        .filter(|filename| embedded_file(filename).is_none())
+        .collect();
+      if !missing.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+          "{} not embedded in the game",
+          missing.join(", ")
+        )));
+      }
+    }
+    #[cfg(all(
+      not(feature = "embedded_assets"),
+      not(target_arch = "wasm32")
+    ))]
     {
       let current_directory = std::env::current_dir()?;
       let assets = current_directory.join("assets");
-      let new_image = assets.join(filename);
-      if !new_image.exists() {
+      let missing: Vec<&str> = filenames
+        .into_iter()
+        .filter(|filename| !assets.join(filename).exists())
+        .collect();
+      if !missing.is_empty() {
         return Err(anyhow::Error::msg(format!(
           "{} not found in assets directory",
-          &filename
+          missing.join(", ")
         )));
       }
     }
+    #[cfg(all(not(feature = "embedded_assets"), target_arch = "wasm32"))]
+    let _ = filenames;
     Ok(())
   }
   //END: check_exists
 
+  // Reads a text file from the `assets` directory, or from the assets
+  // embedded in the game.
+  fn read_asset_file(filename: &str) -> anyhow::Result<String> {
+    #[cfg(feature = "embedded_assets")]
+    let contents = embedded_file(filename)
+      .ok_or_else(|| anyhow::Error::msg("not embedded in the game"))
+      .and_then(|bytes| Ok(String::from_utf8(bytes.to_vec())?));
+    #[cfg(not(feature = "embedded_assets"))]
+    let contents = std::env::current_dir()
+      .and_then(|directory| {
+        std::fs::read_to_string(directory.join("assets").join(filename))
+      })
+      .map_err(anyhow::Error::from);
+    contents.map_err(|e| {
+      anyhow::Error::msg(format!("Unable to read {filename}: {e}"))
+    })
+  }
+
+  // The path the asset server loads `filename` from.
+  pub(crate) fn asset_path(filename: &str) -> String {
+    if cfg!(feature = "embedded_assets") {
+      format!("embedded://{filename}")
+    } else {
+      filename.to_string()
+    }
+  }
+
   //START: load_image
   pub fn add_image<S: ToString>(
     mut self,
@@ -85,36 +272,172 @@ impl AssetManager {
   }
   //END: load_sound
 
+  /// Adds a font (`.ttf` or `.otf`). Fonts are available as `TextFont`s
+  /// with [`AssetStore::text_font`], and are registered with egui once
+  /// loaded. The first font added becomes egui's default.
+  pub fn add_font<S: ToString>(
+    mut self,
+    tag: S,
+    filename: S,
+  ) -> anyhow::Result<Self> {
+    let filename = filename.to_string();
+    AssetManager::asset_exists(&filename)?;
+    self
+        .asset_list
+        .push((tag.to_string(), filename, AssetType::Font));
+    Ok(self)
+  }
+
   //START: AddSpriteSheet
   pub fn add_sprite_sheet<S: ToString>(
-    mut self,
+    self,
     tag: S,
     filename: S,
     sprite_width: f32,
     sprite_height: f32,
     sprites_x: usize,
     sprites_y: usize,
+  ) -> anyhow::Result<Self> {
+    self.add_sprite_sheet_with_layout(tag, filename, SpriteSheetLayout::new(
+      sprite_width, sprite_height, sprites_x, sprites_y))
+  }
+  //END: AddSpriteSheet
+
+  /// Adds a sprite sheet laid out as `layout` describes, for sheets
+  /// whose sprites are spaced apart or don't start in the corner.
+  pub fn add_sprite_sheet_with_layout<S: ToString>(
+    mut self,
+    tag: S,
+    filename: S,
+    layout: SpriteSheetLayout,
   ) -> anyhow::Result<Self> {
     let filename = filename.to_string();
     AssetManager::asset_exists(&filename)?;
     self
         .asset_list
         .push((tag.to_string(), filename, AssetType::SpriteSheet{
-          tile_size: Vec2::new(
-            sprite_width,
-            sprite_height),
-          sprites_x,
-          sprites_y,
+          tile_size: layout.tile_size,
+          sprites_x: layout.sprites_x,
+          sprites_y: layout.sprites_y,
+          padding: layout.padding,
+          offset: layout.offset,
         }));
     Ok(self)
   }
-  //END: AddSpriteSheet
+
+  /// Adds a packed sprite sheet, whose frames can be any size and have
+  /// names, from a TexturePacker or Aseprite JSON descriptor (see
+  /// [`AtlasDescriptor`]). The descriptor names the sheet's image.
+  pub fn add_packed_sprite_sheet<S: ToString>(
+    mut self,
+    tag: S,
+    descriptor: S,
+  ) -> anyhow::Result<Self> {
+    let descriptor = AssetManager::read_atlas_descriptor(
+      &descriptor.to_string(),
+    )?;
+    AssetManager::asset_exists(&descriptor.image)?;
+    self.asset_list.push((
+      tag.to_string(),
+      descriptor.image.clone(),
+      AssetType::PackedSpriteSheet(descriptor),
+    ));
+    Ok(self)
+  }
+
+  // Reads an atlas descriptor in the `assets` directory. The image it
+  // names is relative to the descriptor; it is returned relative to the
+  // `assets` directory, like other filenames.
+  pub(crate) fn read_atlas_descriptor(
+    filename: &str,
+  ) -> anyhow::Result<AtlasDescriptor> {
+    let json = AssetManager::read_asset_file(filename)?;
+    let mut descriptor = AtlasDescriptor::from_json(&json)
+      .map_err(|e| anyhow::Error::msg(format!("{filename}: {e}")))?;
+    if let Some((directory, _)) = filename.rsplit_once('/') {
+      descriptor.image = format!("{directory}/{}", descriptor.image);
+    }
+    Ok(descriptor)
+  }
+
+  /// Adds every asset listed in a manifest file in the `assets`
+  /// directory, so that art can be added without changing any code. The
+  /// format (RON, JSON or TOML) is picked from the file's extension.
+  /// Each asset has a `tag`, a `path` and a `type` (`image`, `sound`,
+  /// `sprite_sheet`, `packed_sprite_sheet` or `font`). Sprite sheets
+  /// also need a `tile_size` (`[width, height]`), `sprites_x` and
+  /// `sprites_y`, and may have a `padding` and an `offset`. A packed
+  /// sprite sheet's `path` is its JSON descriptor. For example, in RON:
+  ///
+  /// ```ron
+  /// (assets: [
+  ///   (tag: "ship", path: "ship.png", type: image),
+  ///   (tag: "flames", path: "flames.png", type: sprite_sheet,
+  ///     tile_size: (16, 24), sprites_x: 4, sprites_y: 1),
+  /// ])
+  /// ```
+  ///
+  /// Any errors in the manifest, and every missing file, are reported
+  /// together.
+  pub fn add_manifest<S: ToString>(
+    mut self,
+    filename: S,
+  ) -> anyhow::Result<Self> {
+    let filename = filename.to_string();
+    let assets = AssetManager::read_manifest(&filename)?;
+    self.asset_list.extend(assets);
+    self.manifests.push(filename);
+    Ok(self)
+  }
+
+  // Reads and checks a manifest file in the `assets` directory.
+  pub(crate) fn read_manifest(
+    filename: &str,
+  ) -> anyhow::Result<Vec<(String, String, AssetType)>> {
+    let format = ManifestFormat::from_filename(filename)?;
+    let manifest = AssetManager::read_asset_file(filename)?;
+    let assets = parse_manifest(
+      &manifest,
+      format,
+      AssetManager::read_atlas_descriptor,
+    )?;
+    AssetManager::assets_exist(
+      assets.iter().map(|(_, filename, _)| filename.as_str()),
+    )?;
+    Ok(assets)
+  }
+
+  /// Adds every asset listed in a manifest that has already been read,
+  /// for example with `include_str!` on platforms (such as the web)
+  /// that can't read the `assets` directory directly. See
+  /// [`AssetManager::add_manifest`] for the manifest's layout. Packed
+  /// sprite sheets still read their descriptors from `assets`.
+  pub fn add_manifest_str(
+    mut self,
+    manifest: &str,
+    format: ManifestFormat,
+  ) -> anyhow::Result<Self> {
+    let assets = parse_manifest(
+      manifest,
+      format,
+      AssetManager::read_atlas_descriptor,
+    )?;
+    AssetManager::assets_exist(
+      assets.iter().map(|(_, filename, _)| filename.as_str()),
+    )?;
+    self.asset_list.extend(assets);
+    Ok(self)
+  }
 }
 
 //START: uncomment
 impl Plugin for AssetManager {
   fn build(&self, app: &mut bevy::prelude::App) {
     app.insert_resource(self.clone());
+    #[cfg(feature = "embedded_assets")]
+    crate::bevy_assets::register_embedded_assets(app);
+    #[cfg(feature = "hot_reload")]
+    app.add_plugins(crate::bevy_assets::HotReloadPlugin);
   }
 }
 //END: uncomment
@@ -128,42 +451,58 @@ pub(crate) fn setup_asset_store(
     asset_index: bevy::platform::collections::HashMap::new(),
     atlases_to_build: Vec::new(),
     atlases: bevy::platform::collections::HashMap::new(),
+    fonts: Vec::new(),
+    groups: asset_resource.groups.clone(),
+    requests: Vec::new(),
   };
-  //START: spawn_sound
   asset_resource.asset_list.iter().for_each(
     |(tag, filename, asset_type)| {
-      //START: AssetSetup
-      match asset_type {
-        AssetType::SpriteSheet { tile_size, sprites_x, sprites_y } => {
-          // Sprite Sheets require that we load the image first, and defer
-          // sheet creation to the loading menu - after the image has loaded
-          let image_handle = asset_server.load_untyped(filename);//<callout id="animation.load_image" />
-          let base_tag = format!("{tag}_base");//<callout id="animation.tag_base_image" />
-          assets
-              .asset_index
-              .insert(base_tag.clone(), image_handle);//<callout id="animation.insert_image" />
-
-          // Now that its loaded, we store the future atlas in the asset store
-          assets.atlases_to_build.push(FutureAtlas {//<callout id="animation.future_atlas" />
-            tag: tag.clone(),
-            texture_tag: base_tag,
-            tile_size: *tile_size,
-            sprites_x: *sprites_x,
-            sprites_y: *sprites_y,
-          });
-        }
-        //END: AssetSetup
-        _ => {
-          // Most asset types don't require a separate loader
-          //END: spawn_sound
-          assets
-              .asset_index
-              .insert(tag.clone(), asset_server.load_untyped(filename));
-        }
-      }
+      load_asset(&mut assets, asset_server, tag, filename, asset_type);
     },
   );
+  // Hot reloading needs the asset list to compare changed manifests with
+  #[cfg(not(feature = "hot_reload"))]
   commands.remove_resource::<AssetManager>();//<callout id="asset_mgr_remove_self" />
   commands.insert_resource(assets.clone());//<callout id="asset_mgr_add_store" />
   assets
-}
\ No newline at end of file
+}
+
+// Starts loading an asset, and adds it to the store.
+pub(crate) fn load_asset(
+  assets: &mut AssetStore,
+  asset_server: &AssetServer,
+  tag: &str,
+  filename: &str,
+  asset_type: &AssetType,
+) {
+  //START: spawn_sound
+  //START: AssetSetup
+  match asset_type {
+    AssetType::SpriteSheet { .. } | AssetType::PackedSpriteSheet(_) => {
+      // Sprite Sheets require that we load the image first, and defer
+      // sheet creation to the loading menu - after the image has loaded
+      let image_handle = asset_server.load_untyped(AssetManager::asset_path(filename));//<callout id="animation.load_image" />
+      let base_tag = format!("{tag}_base");//<callout id="animation.tag_base_image" />
+      assets
+          .asset_index
+          .insert(base_tag, image_handle);//<callout id="animation.insert_image" />
+
+      // Now that its loaded, we store the future atlas in the asset store
+      assets.atlases_to_build.extend(FutureAtlas::new(tag, asset_type));//<callout id="animation.future_atlas" />
+    }
+    //END: AssetSetup
+    AssetType::Font => {
+      assets.fonts.push(tag.to_string());
+      assets
+          .asset_index
+          .insert(tag.to_string(), asset_server.load_untyped(AssetManager::asset_path(filename)));
+    }
+    _ => {
+      // Most asset types don't require a separate loader
+      //END: spawn_sound
+      assets
+          .asset_index
+          .insert(tag.to_string(), asset_server.load_untyped(AssetManager::asset_path(filename)));
+    }
+  }
+}
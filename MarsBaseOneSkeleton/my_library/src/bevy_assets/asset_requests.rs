@@ -0,0 +1,192 @@
+// Assets requested while the game runs, after the loading menu. They
+// load in the background, and are added to the store under new tags.
+use crate::{AssetLoadFailed, AssetManager, AssetStore, AssetType};
+use crate::{bevy_assets::load_asset, LoadedAssets};
+use bevy::{asset::LoadState, log, prelude::*};
+
+/// Sent when an asset requested with [`AssetStore::request`] has loaded
+/// and can be used. Requests that fail send
+/// [`AssetLoadFailed`](crate::AssetLoadFailed) instead.
+#[derive(Event, Debug, Clone)]
+pub struct AssetReady {
+  /// The tag the asset was requested with.
+  pub tag: String,
+}
+
+impl AssetStore {
+  /// Starts loading `filename` under a new tag while the game is
+  /// running, for example for a skin the player picked. The game keeps
+  /// running while it loads: [`AssetStore::is_loading`] reports progress,
+  /// and an [`AssetReady`] event is sent once it can be used. Sprite
+  /// sheets are built as soon as their image has loaded. Fonts requested
+  /// this way can be used as `TextFont`s, but aren't added to egui.
+  pub fn request<S: ToString>(
+    &mut self,
+    tag: S,
+    filename: S,
+    asset_type: AssetType,
+    asset_server: &AssetServer,
+  ) -> anyhow::Result<()> {
+    let tag = tag.to_string();
+    let filename = filename.to_string();
+    let in_use = self.asset_index.contains_key(&tag)
+      || self.atlases_to_build.iter().any(|atlas| atlas.tag == tag);
+    if in_use {
+      return Err(anyhow::Error::msg(format!(
+        "Asset tag {tag} is already in use"
+      )));
+    }
+    AssetManager::assets_exist([filename.as_str()])?;
+    load_asset(self, asset_server, &tag, &filename, &asset_type);
+    self.requests.push(tag);
+    Ok(())
+  }
+
+  /// Returns `true` while the asset requested as `tag` is loading.
+  pub fn is_loading(&self, tag: &str) -> bool {
+    self.requests.iter().any(|request| request == tag)
+  }
+
+  /// How many requested assets are still loading.
+  pub fn pending_requests(&self) -> usize {
+    self.requests.len()
+  }
+}
+
+// Checks on requested assets, building the sprite sheets whose images
+// have loaded.
+pub(crate) fn load_requests(
+  mut store: ResMut<AssetStore>,
+  asset_server: Res<AssetServer>,
+  loaded_assets: Res<LoadedAssets>,
+  mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+  mut ready: EventWriter<AssetReady>,
+  mut failures: EventWriter<AssetLoadFailed>,
+) {
+  if store.requests.is_empty() {
+    return;
+  }
+  for tag in std::mem::take(&mut store.requests) {
+    let atlas = store
+      .atlases_to_build
+      .iter()
+      .find(|atlas| atlas.tag == tag)
+      .cloned();
+    let index_tag = match &atlas {
+      Some(atlas) => atlas.texture_tag.clone(),
+      None => tag.clone(),
+    };
+    let load_state = store
+      .asset_index
+      .get(&index_tag)
+      .and_then(|handle| asset_server.get_load_state(handle.id()));
+    match load_state {
+      Some(LoadState::Loaded) => {
+        if let Some(atlas) = atlas {
+          let Some(image) = store.get_handle(&index_tag, &loaded_assets)
+          else {
+            store.requests.push(tag);
+            continue;
+          };
+          let layout = layouts.add(atlas.layout);
+          store.atlases.insert(tag.clone(), (image, layout));
+        }
+        ready.write(AssetReady { tag });
+      }
+      Some(LoadState::Failed(error)) => {
+        // Forget the request, so the tag can be requested again
+        store.asset_index.remove(&index_tag);
+        store.atlases_to_build.retain(|atlas| atlas.tag != tag);
+        store.fonts.retain(|font| *font != tag);
+        let failure = AssetLoadFailed { tag, error: error.to_string() };
+        log::error!(
+          "Unable to load asset {}: {}",
+          failure.tag,
+          failure.error
+        );
+        failures.write(failure);
+      }
+      // The asset was released or replaced by something else
+      None => {}
+      _ => store.requests.push(tag),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use bevy::platform::collections::HashMap;
+
+  #[test]
+  fn test_request_errors() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    let asset_server = app.world().resource::<AssetServer>().clone();
+    let mut store = AssetStore {
+      asset_index: HashMap::from_iter([("ship".to_string(), default())]),
+      atlases_to_build: Vec::new(),
+      atlases: HashMap::new(),
+      fonts: Vec::new(),
+      groups: Vec::new(),
+      requests: Vec::new(),
+    };
+
+    let error = store
+      .request("ship", "skin.png", AssetType::Image, &asset_server)
+      .unwrap_err();
+    assert_eq!(error.to_string(), "Asset tag ship is already in use");
+    let error = store
+      .request("skin", "no_such_skin.png", AssetType::Image, &asset_server)
+      .unwrap_err();
+    assert!(error.to_string().contains("no_such_skin.png"), "{error}");
+    assert!(!store.is_loading("skin"));
+    assert_eq!(store.pending_requests(), 0);
+  }
+
+  #[test]
+  fn test_failed_request() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.add_event::<AssetReady>().add_event::<AssetLoadFailed>();
+    app.init_asset::<TextureAtlasLayout>();
+    let asset_server = app.world().resource::<AssetServer>().clone();
+    let mut store = AssetStore {
+      asset_index: HashMap::new(),
+      atlases_to_build: Vec::new(),
+      atlases: HashMap::new(),
+      fonts: Vec::new(),
+      groups: Vec::new(),
+      requests: vec!["skin".to_string()],
+    };
+    let sheet = AssetType::SpriteSheet {
+      tile_size: Vec2::splat(16.0),
+      sprites_x: 4,
+      sprites_y: 1,
+      padding: Vec2::ZERO,
+      offset: Vec2::ZERO,
+    };
+    // Skips `request`'s check that the file exists, so loading fails
+    load_asset(&mut store, &asset_server, "skin", "missing.png", &sheet);
+    app.insert_resource(store);
+    app.add_systems(Update, load_requests);
+
+    for _ in 0..100 {
+      app.update();
+      if app.world().resource::<AssetStore>().pending_requests() == 0 {
+        break;
+      }
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let failures = app.world().resource::<Events<AssetLoadFailed>>();
+    let failure = failures.iter_current_update_events().next().unwrap();
+    assert_eq!(failure.tag, "skin");
+    let mut store = app.world_mut().resource_mut::<AssetStore>();
+    assert!(store.asset_index.is_empty());
+    assert!(store.atlases_to_build.is_empty());
+    let error = store
+      .request("skin", "skin.png", AssetType::Image, &asset_server)
+      .unwrap_err();
+    assert!(!error.to_string().contains("already in use"), "{error}");
+  }
+}
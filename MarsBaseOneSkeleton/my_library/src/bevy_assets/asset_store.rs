@@ -1,4 +1,5 @@
 use bevy::{asset::{Asset, LoadedUntypedAsset}, prelude::*, platform::collections::HashMap};
+use crate::{AssetGroup, AssetManager, AssetType};
 
 pub type LoadedAssets = Assets<LoadedUntypedAsset>;
 pub type AssetResource<'w> = Res<'w, LoadedAssets>;
@@ -7,9 +8,62 @@ pub type AssetResource<'w> = Res<'w, LoadedAssets>;
 pub(crate) struct FutureAtlas {
   pub(crate) tag: String,
   pub(crate) texture_tag: String,
-  pub(crate) tile_size: Vec2,
-  pub(crate) sprites_x: usize,
-  pub(crate) sprites_y: usize,
+  pub(crate) layout: TextureAtlasLayout,
+  // Named frames, from packed sprite sheets
+  pub(crate) frames: HashMap<String, usize>,
+  // The tile size, padding and offset of grid sprite sheets
+  #[cfg_attr(not(feature = "hot_reload"), allow(dead_code))]
+  pub(crate) grid: Option<(UVec2, UVec2, UVec2)>,
+}
+
+impl FutureAtlas {
+  // Describes the atlas for a sprite sheet, or returns `None` if
+  // `asset_type` isn't a sprite sheet.
+  pub(crate) fn new(tag: &str, asset_type: &AssetType) -> Option<Self> {
+    let (layout, frames, grid) = match asset_type {
+      AssetType::SpriteSheet { tile_size, sprites_x, sprites_y, padding, offset } => {
+        let layout = TextureAtlasLayout::from_grid(
+          tile_size.as_uvec2(),
+          *sprites_x as u32,
+          *sprites_y as u32,
+          Some(padding.as_uvec2()), Some(offset.as_uvec2()));
+        let grid =
+          (tile_size.as_uvec2(), padding.as_uvec2(), offset.as_uvec2());
+        (layout, HashMap::new(), Some(grid))
+      }
+      AssetType::PackedSpriteSheet(descriptor) => {
+        (descriptor.layout(), descriptor.frame_names(), None)
+      }
+      _ => return None,
+    };
+    Some(Self {
+      tag: tag.to_string(),
+      texture_tag: format!("{tag}_base"),
+      layout,
+      frames,
+      grid,
+    })
+  }
+
+  // Lays a grid sprite sheet out again for an image of `size`, with as
+  // many sprites as fit. Returns `None` for packed sprite sheets, whose
+  // frames are listed, or if not even one sprite fits.
+  #[cfg(feature = "hot_reload")]
+  pub(crate) fn relayout(&self, size: UVec2) -> Option<TextureAtlasLayout> {
+    let (tile_size, padding, offset) = self.grid?;
+    let fit =
+      (size.saturating_sub(offset) + padding) / (tile_size + padding);
+    if fit.x == 0 || fit.y == 0 {
+      return None;
+    }
+    Some(TextureAtlasLayout::from_grid(
+      tile_size,
+      fit.x,
+      fit.y,
+      Some(padding),
+      Some(offset),
+    ))
+  }
 }
 
 #[derive(Resource, Clone)]
@@ -17,39 +71,250 @@ pub struct AssetStore {
   pub(crate) asset_index: HashMap<String, Handle<LoadedUntypedAsset>>,
   pub(crate) atlases_to_build: Vec<FutureAtlas>,
   pub(crate) atlases: HashMap<String, (Handle<Image>, Handle<TextureAtlasLayout>)>,
+  // Font tags, in the order they were added
+  pub(crate) fonts: Vec<String>,
+  pub(crate) groups: Vec<AssetGroup>,
+  // Tags requested at runtime that are still loading
+  pub(crate) requests: Vec<String>,
+}
+
+/// Why an [`AssetStore`] lookup failed. Each variant holds the tag that
+/// was looked up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetError {
+  /// No asset was added with this tag.
+  UnknownTag(String),
+  /// The asset exists, but hasn't finished loading.
+  NotLoaded(String),
+  /// The asset isn't of the requested type.
+  WrongType { tag: String, expected: &'static str },
+  /// The sprite sheet has no frame with this name.
+  UnknownFrame { tag: String, frame: String },
+}
+
+impl std::fmt::Display for AssetError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AssetError::UnknownTag(tag) => write!(f, "unknown asset tag \"{tag}\""),
+      AssetError::NotLoaded(tag) => {
+        write!(f, "asset \"{tag}\" hasn't finished loading")
+      }
+      AssetError::WrongType { tag, expected } => {
+        write!(f, "asset \"{tag}\" is the wrong type (expected {expected})")
+      }
+      AssetError::UnknownFrame { tag, frame } => {
+        write!(f, "sprite sheet \"{tag}\" has no frame named \"{frame}\"")
+      }
+    }
+  }
+}
+
+impl std::error::Error for AssetError {}
+
+// The last part of a type's name ("Image" rather than
+// "bevy_image::image::Image").
+fn short_type_name<T>() -> &'static str {
+  let name = std::any::type_name::<T>();
+  name.rsplit("::").next().unwrap_or(name)
 }
 
 impl AssetStore {
+  /// Looks up the asset tagged `index`, or returns `None` if it can't be
+  /// used. [`AssetStore::try_get_handle`] explains why.
   pub fn get_handle<T>(&self, index: &str, assets: &LoadedAssets) -> Option<Handle<T>>
   where
       T: Asset,
   {
-    if let Some(handle_untyped) = self.asset_index.get(index) {
-      if let Some(handle) = assets.get(handle_untyped) {
-        return Some(handle.handle.clone().typed::<T>());
-      }
-      None
-    } else {
-      None
-    }
+    self.try_get_handle(index, assets).ok()
+  }
+
+  /// Looks up the asset tagged `tag`, with an error saying whether the
+  /// tag is unknown, the asset is still loading, or it isn't a `T`.
+  pub fn try_get_handle<T>(
+    &self,
+    tag: &str,
+    assets: &LoadedAssets,
+  ) -> Result<Handle<T>, AssetError>
+  where
+      T: Asset,
+  {
+    let handle = self.asset_index.get(tag)
+      .ok_or_else(|| AssetError::UnknownTag(tag.to_string()))?;
+    let loaded = assets.get(handle)
+      .ok_or_else(|| AssetError::NotLoaded(tag.to_string()))?;
+    loaded.handle.clone().try_typed::<T>().map_err(|_| {
+      AssetError::WrongType { tag: tag.to_string(), expected: short_type_name::<T>() }
+    })
   }
 
   pub fn get_atlas_handle(&self, index: &str) -> Option<(Handle<Image>, Handle<TextureAtlasLayout>)>
   {
-    if let Some(handle) = self.atlases.get(index) {
-      return Some(handle.clone());
+    self.try_get_atlas_handle(index).ok()
+  }
+
+  /// Looks up the sprite sheet tagged `tag`, with an error saying whether
+  /// the tag is unknown, the sheet hasn't been built yet, or the tag
+  /// isn't a sprite sheet.
+  pub fn try_get_atlas_handle(
+    &self,
+    tag: &str,
+  ) -> Result<(Handle<Image>, Handle<TextureAtlasLayout>), AssetError> {
+    if let Some(handle) = self.atlases.get(tag) {
+      Ok(handle.clone())
+    } else if self.atlases_to_build.iter().any(|atlas| atlas.tag == tag) {
+      Err(AssetError::NotLoaded(tag.to_string()))
+    } else if self.asset_index.contains_key(tag) {
+      Err(AssetError::WrongType { tag: tag.to_string(), expected: "sprite sheet" })
+    } else {
+      Err(AssetError::UnknownTag(tag.to_string()))
     }
-    None
   }
 
+  /// Finds the index of the frame called `frame` in the packed sprite
+  /// sheet tagged `tag`, for use with `TextureAtlas::index`.
+  pub fn try_get_frame_index(
+    &self,
+    tag: &str,
+    frame: &str,
+  ) -> Result<usize, AssetError> {
+    let atlas = self.atlases_to_build.iter()
+      .find(|atlas| atlas.tag == tag)
+      .ok_or_else(|| AssetError::UnknownTag(tag.to_string()))?;
+    atlas.frames.get(frame).copied().ok_or_else(|| {
+      AssetError::UnknownFrame { tag: tag.to_string(), frame: frame.to_string() }
+    })
+  }
+
+  // Finds a named frame in whichever sprite sheet uses `layout`. A
+  // layout that isn't in the store is reported as an unknown (empty) tag.
+  pub(crate) fn frame_index_in_layout(
+    &self,
+    layout: &Handle<TextureAtlasLayout>,
+    frame: &str,
+  ) -> Result<usize, AssetError> {
+    let tag = self.atlases.iter()
+      .find(|(_, (_, atlas_layout))| atlas_layout.id() == layout.id())
+      .map(|(tag, _)| tag.as_str())
+      .unwrap_or_default();
+    self.try_get_frame_index(tag, frame)
+  }
+
+  /// Returns a `TextFont` using the font tagged `tag`, for Bevy text.
+  pub fn text_font(
+    &self,
+    tag: &str,
+    font_size: f32,
+    assets: &LoadedAssets,
+  ) -> Result<TextFont, AssetError> {
+    Ok(TextFont {
+      font: self.try_get_handle(tag, assets)?,
+      font_size,
+      ..default()
+    })
+  }
+
+  /// Loads `filename` in place of the asset tagged `tag`, for example to
+  /// substitute a placeholder for an asset that failed to load (see
+  /// [`AssetLoadFailed`](crate::AssetLoadFailed)). The loading menu waits
+  /// for the replacement before continuing.
+  pub fn replace<S: ToString>(
+    &mut self,
+    tag: S,
+    filename: S,
+    asset_server: &AssetServer,
+  ) {
+    self.asset_index.insert(
+      tag.to_string(),
+      asset_server
+        .load_untyped(AssetManager::asset_path(&filename.to_string())),
+    );
+  }
+
+  /// Plays a sound effect once, on the mixer's `Sfx` bus. See
+  /// [`AssetStore::play_on`] for other buses.
   pub fn play(&self,
               sound_name: &str,
               commands: &mut Commands,
               assets: &LoadedAssets
   ) {
-    let sound_handle: Handle<AudioSource> = self.get_handle(sound_name, assets).unwrap();
-    commands.spawn((
-      AudioPlayer::new(sound_handle.clone()),
+    self.play_on(sound_name, crate::AudioBus::Sfx, commands, assets);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::AtlasDescriptor;
+
+  #[test]
+  fn test_lookup_errors() {
+    let mut assets = LoadedAssets::default();
+    let ship = assets.add(LoadedUntypedAsset {
+      handle: Handle::<Image>::default().untyped(),
+    });
+    let pending = assets.add(LoadedUntypedAsset {
+      handle: Handle::<Image>::default().untyped(),
+    });
+    assets.remove(&pending);
+    let store = AssetStore {
+      asset_index: HashMap::from_iter([
+        ("ship".to_string(), ship),
+        ("pending".to_string(), pending),
+      ]),
+      atlases_to_build: Vec::from_iter(FutureAtlas::new(
+        "flames",
+        &AssetType::PackedSpriteSheet(AtlasDescriptor {
+          image: "flames.png".to_string(),
+          size: UVec2::new(32, 8),
+          frames: vec![
+            ("small".to_string(), URect::new(0, 0, 8, 8)),
+            ("large".to_string(), URect::new(8, 0, 32, 8)),
+          ],
+        }),
+      )),
+      atlases: HashMap::new(),
+      fonts: Vec::new(),
+      groups: Vec::new(),
+      requests: Vec::new(),
+    };
+
+    assert!(store.try_get_handle::<Image>("ship", &assets).is_ok());
+    assert_eq!(
+      store.try_get_handle::<Image>("shp", &assets),
+      Err(AssetError::UnknownTag("shp".to_string()))
+    );
+    assert_eq!(
+      store.try_get_handle::<Image>("pending", &assets),
+      Err(AssetError::NotLoaded("pending".to_string()))
+    );
+    let error = store
+      .try_get_handle::<AudioSource>("ship", &assets)
+      .unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "asset \"ship\" is the wrong type (expected AudioSource)"
+    );
+
+    assert_eq!(
+      store.try_get_atlas_handle("flames"),
+      Err(AssetError::NotLoaded("flames".to_string()))
+    );
+    assert!(matches!(
+      store.try_get_atlas_handle("ship"),
+      Err(AssetError::WrongType { .. })
     ));
+    assert_eq!(
+      store.try_get_atlas_handle("smoke"),
+      Err(AssetError::UnknownTag("smoke".to_string()))
+    );
+
+    assert_eq!(store.try_get_frame_index("flames", "large"), Ok(1));
+    assert_eq!(
+      store.try_get_frame_index("flames", "huge"),
+      Err(AssetError::UnknownFrame {
+        tag: "flames".to_string(),
+        frame: "huge".to_string()
+      })
+    );
   }
 }
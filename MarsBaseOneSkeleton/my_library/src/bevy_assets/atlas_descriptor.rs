@@ -0,0 +1,231 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+/// The frames of a packed sprite sheet, read from a JSON descriptor as
+/// exported by TexturePacker or Aseprite (in either the "hash" or the
+/// "array" layout). Frames keep the order they have in the file, and can
+/// be looked up by name or by index.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::AtlasDescriptor;
+/// let json = r#"{
+///   "frames": {
+///     "idle.png": { "frame": { "x": 0, "y": 0, "w": 16, "h": 24 } },
+///     "run.png": { "frame": { "x": 16, "y": 0, "w": 20, "h": 24 } }
+///   },
+///   "meta": { "image": "hero.png", "size": { "w": 36, "h": 24 } }
+/// }"#;
+/// let atlas = AtlasDescriptor::from_json(json).unwrap();
+/// assert_eq!(atlas.image, "hero.png");
+/// assert_eq!(atlas.frame_index("run.png"), Some(1));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasDescriptor {
+  /// The sheet's image, relative to the descriptor.
+  pub image: String,
+  /// The size of the sheet's image, in pixels.
+  pub size: UVec2,
+  /// Each frame's name and position in the image.
+  pub frames: Vec<(String, URect)>,
+}
+
+impl AtlasDescriptor {
+  /// Reads a TexturePacker or Aseprite JSON descriptor. Rotated frames
+  /// aren't supported; export the sheet without rotation.
+  pub fn from_json(json: &str) -> anyhow::Result<Self> {
+    let file: DescriptorFile = serde_json::from_str(json)?;
+    let mut frames = Vec::with_capacity(file.frames.0.len());
+    for (name, entry) in file.frames.0 {
+      if entry.rotated {
+        return Err(anyhow::Error::msg(format!(
+          "Frame {name} is rotated, which isn't supported"
+        )));
+      }
+      let Rect { x, y, w, h } = entry.frame;
+      frames.push((name, URect::new(x, y, x + w, y + h)));
+    }
+    // Older exports may leave out the size, so fall back to the extent
+    // of the frames
+    let size = file.meta.size.map(|size| UVec2::new(size.w, size.h));
+    let size = size.unwrap_or_else(|| {
+      frames
+        .iter()
+        .fold(UVec2::ZERO, |size, (_, frame)| size.max(frame.max))
+    });
+    Ok(Self {
+      image: file.meta.image,
+      size,
+      frames,
+    })
+  }
+
+  /// Finds the index of the frame called `name`.
+  pub fn frame_index(&self, name: &str) -> Option<usize> {
+    self.frames.iter().position(|(frame, _)| frame == name)
+  }
+
+  pub(crate) fn layout(&self) -> TextureAtlasLayout {
+    let mut layout = TextureAtlasLayout::new_empty(self.size);
+    for (_, frame) in self.frames.iter() {
+      layout.add_texture(*frame);
+    }
+    layout
+  }
+
+  pub(crate) fn frame_names(&self) -> HashMap<String, usize> {
+    self
+      .frames
+      .iter()
+      .enumerate()
+      .map(|(index, (name, _))| (name.clone(), index))
+      .collect()
+  }
+}
+
+#[derive(Deserialize)]
+struct DescriptorFile {
+  frames: Frames,
+  meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+  image: String,
+  size: Option<Size>,
+}
+
+#[derive(Deserialize)]
+struct Size {
+  w: u32,
+  h: u32,
+}
+
+#[derive(Deserialize)]
+struct Rect {
+  x: u32,
+  y: u32,
+  w: u32,
+  h: u32,
+}
+
+#[derive(Deserialize)]
+struct FrameEntry {
+  // Only present in the "array" layout
+  filename: Option<String>,
+  frame: Rect,
+  #[serde(default)]
+  rotated: bool,
+}
+
+// Frames are either a map from name to frame ("hash"), or a list of
+// frames that each carry a `filename` ("array"). Both are read in file
+// order, which a map would otherwise lose.
+struct Frames(Vec<(String, FrameEntry)>);
+
+impl<'de> Deserialize<'de> for Frames {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(FramesVisitor)
+  }
+}
+
+struct FramesVisitor;
+
+impl<'de> Visitor<'de> for FramesVisitor {
+  type Value = Frames;
+
+  fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.write_str("a map or list of frames")
+  }
+
+  fn visit_map<A: MapAccess<'de>>(
+    self,
+    mut map: A,
+  ) -> Result<Frames, A::Error> {
+    let mut frames = Vec::new();
+    while let Some(frame) = map.next_entry::<String, FrameEntry>()? {
+      frames.push(frame);
+    }
+    Ok(Frames(frames))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(
+    self,
+    mut seq: A,
+  ) -> Result<Frames, A::Error> {
+    let mut frames = Vec::new();
+    while let Some(entry) = seq.next_element::<FrameEntry>()? {
+      let name = entry
+        .filename
+        .clone()
+        .ok_or_else(|| de::Error::missing_field("filename"))?;
+      frames.push((name, entry));
+    }
+    Ok(Frames(frames))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_hash_keeps_file_order() {
+    let json = r#"{
+      "frames": {
+        "walk 10.aseprite": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } },
+        "walk 2.aseprite": { "frame": { "x": 8, "y": 0, "w": 8, "h": 8 } }
+      },
+      "meta": { "image": "walk.png" }
+    }"#;
+    let atlas = AtlasDescriptor::from_json(json).unwrap();
+    assert_eq!(atlas.frame_index("walk 10.aseprite"), Some(0));
+    assert_eq!(atlas.frame_index("walk 2.aseprite"), Some(1));
+    assert_eq!(atlas.frame_index("walk 3.aseprite"), None);
+    // Without a size in the file, the frames' extent is used
+    assert_eq!(atlas.size, UVec2::new(16, 8));
+  }
+
+  #[test]
+  fn test_array() {
+    let json = r#"{
+      "frames": [
+        { "filename": "a", "frame": { "x": 2, "y": 4, "w": 10, "h": 12 },
+          "rotated": false, "trimmed": false },
+        { "filename": "b", "frame": { "x": 12, "y": 4, "w": 5, "h": 6 } }
+      ],
+      "meta": { "image": "sheet.png", "size": { "w": 32, "h": 32 } }
+    }"#;
+    let atlas = AtlasDescriptor::from_json(json).unwrap();
+    assert_eq!(atlas.size, UVec2::new(32, 32));
+    assert_eq!(
+      atlas.frames,
+      vec![
+        ("a".to_string(), URect::new(2, 4, 12, 16)),
+        ("b".to_string(), URect::new(12, 4, 17, 10)),
+      ]
+    );
+    let layout = atlas.layout();
+    assert_eq!(layout.len(), 2);
+    assert_eq!(layout.textures[1], URect::new(12, 4, 17, 10));
+  }
+
+  #[test]
+  fn test_errors() {
+    let rotated = r#"{
+      "frames": { "a": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 },
+        "rotated": true } },
+      "meta": { "image": "sheet.png" }
+    }"#;
+    assert!(AtlasDescriptor::from_json(rotated).is_err());
+    let unnamed = r#"{
+      "frames": [ { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } } ],
+      "meta": { "image": "sheet.png" }
+    }"#;
+    assert!(AtlasDescriptor::from_json(unnamed).is_err());
+  }
+}
@@ -0,0 +1,56 @@
+// Assets compiled into the game, enabled by the `embedded_assets`
+// feature. They are served through Bevy's `embedded://` asset source, so
+// the game runs from any working directory.
+use bevy::{
+  asset::io::embedded::EmbeddedAssetRegistry,
+  log,
+  platform::collections::HashMap,
+  prelude::*,
+};
+use std::{path::Path, sync::OnceLock};
+
+static EMBEDDED_ASSETS: OnceLock<HashMap<&'static str, &'static [u8]>> =
+  OnceLock::new();
+
+/// Uses `files` (pairs of a filename in the `assets` directory and its
+/// contents) in place of the `assets` directory. Call this with the
+/// [`embed_assets!`](crate::embed_assets) macro, before building an
+/// `AssetManager`. Only the first call has any effect.
+pub fn use_embedded_assets(files: &'static [(&'static str, &'static [u8])]) {
+  let files = files
+    .iter()
+    .map(|(filename, bytes)| (*filename, *bytes))
+    .collect();
+  if EMBEDDED_ASSETS.set(files).is_err() {
+    log::warn!("Embedded assets were already set");
+  }
+}
+
+// The contents of an embedded file, by its name in the `assets`
+// directory.
+pub(crate) fn embedded_file(filename: &str) -> Option<&'static [u8]> {
+  EMBEDDED_ASSETS.get()?.get(filename).copied()
+}
+
+// Adds every embedded file to Bevy's embedded asset source.
+pub(crate) fn register_embedded_assets(app: &mut App) {
+  let Some(files) = EMBEDDED_ASSETS.get() else {
+    log::error!(
+      "The embedded_assets feature is enabled, but embed_assets! wasn't \
+      called"
+    );
+    return;
+  };
+  let Some(registry) = app.world().get_resource::<EmbeddedAssetRegistry>()
+  else {
+    log::error!(
+      "Unable to embed assets: add the AssetManager plugin after \
+      DefaultPlugins (or AssetPlugin)"
+    );
+    return;
+  };
+  for (filename, bytes) in files.iter() {
+    let path = Path::new(filename);
+    registry.insert_asset(path.to_path_buf(), path, *bytes);
+  }
+}
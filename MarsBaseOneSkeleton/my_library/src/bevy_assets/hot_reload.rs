@@ -0,0 +1,300 @@
+// Live reloading for development, enabled by the `hot_reload` feature.
+// Bevy's file watcher reloads edited images and sounds in place, so
+// sprites using them update by themselves. This lays grid sprite sheets
+// out again when their image changes size, and applies edits to asset
+// manifests.
+use crate::{AssetManager, AssetStore, AssetType, FutureAtlas};
+use bevy::{log, platform::collections::HashMap, prelude::*};
+use std::time::SystemTime;
+
+// How often manifest files are checked for changes, in seconds.
+const MANIFEST_POLL_SECONDS: f32 = 1.0;
+
+pub(crate) struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+  fn build(&self, app: &mut App) {
+    app.insert_resource(ManifestWatch {
+      timer: Timer::from_seconds(
+        MANIFEST_POLL_SECONDS,
+        TimerMode::Repeating,
+      ),
+      modified: HashMap::new(),
+    });
+    app.add_systems(
+      Update,
+      (reload_manifests, rebuild_changed_atlases)
+        .chain()
+        .run_if(resource_exists::<AssetStore>),
+    );
+  }
+}
+
+// Sprite sheets are laid out again when their image changes size. An
+// image replaced by a manifest edit is new, and keeps the manifest's
+// layout.
+fn rebuild_changed_atlases(
+  mut sizes: Local<HashMap<AssetId<Image>, UVec2>>,
+  mut store: ResMut<AssetStore>,
+  images: Res<Assets<Image>>,
+  mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+  let store = &mut *store;
+  for atlas in store.atlases_to_build.iter_mut() {
+    let Some((handle, layout)) = store.atlases.get(&atlas.tag) else {
+      continue;
+    };
+    let Some(image) = images.get(handle) else {
+      continue;
+    };
+    let resized = sizes
+      .insert(handle.id(), image.size())
+      .is_some_and(|size| size != image.size());
+    if resized {
+      rebuild_layout(atlas, layout, &mut layouts, image.size());
+    }
+  }
+}
+
+// Fits a sprite sheet's layout to its edited image. Grid sheets gain or
+// lose sprites as the image grows or shrinks; packed sheets list their
+// frames, so they only warn if the image no longer holds them.
+fn rebuild_layout(
+  atlas: &mut FutureAtlas,
+  layout: &Handle<TextureAtlasLayout>,
+  layouts: &mut Assets<TextureAtlasLayout>,
+  image_size: UVec2,
+) {
+  let Some(new_layout) = atlas.relayout(image_size) else {
+    let fits = atlas
+      .layout
+      .textures
+      .iter()
+      .all(|rect| rect.max.cmple(image_size).all());
+    if !fits {
+      log::warn!(
+        "Sprite sheet {} is {}x{}, which is too small for its frames",
+        atlas.tag,
+        image_size.x,
+        image_size.y
+      );
+    }
+    return;
+  };
+  if new_layout.textures == atlas.layout.textures {
+    return;
+  }
+  log::info!(
+    "Rebuilt sprite sheet {} with {} sprites",
+    atlas.tag,
+    new_layout.textures.len()
+  );
+  layouts.insert(layout.id(), new_layout.clone());
+  atlas.layout = new_layout;
+}
+
+#[derive(Resource)]
+struct ManifestWatch {
+  timer: Timer,
+  modified: HashMap<String, SystemTime>,
+}
+
+impl ManifestWatch {
+  // Records when a manifest was last modified, and returns `true` if
+  // that has changed since the last check. The first check only
+  // records the time.
+  fn changed(&mut self, filename: &str) -> bool {
+    let Ok(directory) = std::env::current_dir() else {
+      return false;
+    };
+    let path = directory.join("assets").join(filename);
+    let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified())
+    else {
+      return false;
+    };
+    self
+      .modified
+      .insert(filename.to_string(), modified)
+      .is_some_and(|previous| previous != modified)
+  }
+}
+
+fn reload_manifests(
+  time: Res<Time>,
+  mut watch: ResMut<ManifestWatch>,
+  manager: Option<ResMut<AssetManager>>,
+  mut store: ResMut<AssetStore>,
+  asset_server: Res<AssetServer>,
+  mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+  mut sprites: Query<&mut Sprite>,
+) {
+  if !watch.timer.tick(time.delta()).just_finished() {
+    return;
+  }
+  let Some(mut manager) = manager else {
+    return;
+  };
+  for filename in manager.manifests.clone() {
+    if !watch.changed(&filename) {
+      continue;
+    }
+    // A bad edit is reported, and the game keeps its current assets
+    match AssetManager::read_manifest(&filename) {
+      Ok(assets) => {
+        let mut changed = false;
+        for entry in assets {
+          changed |= apply(
+            entry,
+            &mut manager,
+            &mut store,
+            &asset_server,
+            &mut layouts,
+            &mut sprites,
+          );
+        }
+        if changed {
+          log::info!("Reloaded assets from {filename}");
+        }
+      }
+      Err(e) => log::error!("Unable to reload {filename}: {e}"),
+    }
+  }
+}
+
+// Loads a manifest entry if it is new or has changed, returning `true`
+// if it did.
+fn apply(
+  (tag, path, asset_type): (String, String, AssetType),
+  manager: &mut AssetManager,
+  store: &mut AssetStore,
+  asset_server: &AssetServer,
+  layouts: &mut Assets<TextureAtlasLayout>,
+  sprites: &mut Query<&mut Sprite>,
+) -> bool {
+  let existing = manager.asset_list.iter().position(|(t, _, _)| *t == tag);
+  let old_path = match existing {
+    Some(index) => {
+      let (_, old_path, old_type) = &manager.asset_list[index];
+      if *old_path == path && *old_type == asset_type {
+        return false;
+      }
+      Some(old_path.clone())
+    }
+    None => None,
+  };
+  let entry = (tag.clone(), path.clone(), asset_type.clone());
+  match existing {
+    Some(index) => manager.asset_list[index] = entry,
+    None => manager.asset_list.push(entry),
+  }
+
+  match asset_type {
+    AssetType::SpriteSheet { .. } | AssetType::PackedSpriteSheet(_) => {
+      let Some(atlas) = FutureAtlas::new(&tag, &asset_type) else {
+        return false;
+      };
+      store.replace(atlas.texture_tag.clone(), path.clone(), asset_server);
+      let image: Handle<Image> = asset_server.load(path);
+      let layout = match store.atlases.get(&tag) {
+        Some((old_image, layout)) => {
+          swap_image(sprites, old_image.id(), &image);
+          layouts.insert(layout.id(), atlas.layout.clone());
+          layout.clone()
+        }
+        None => layouts.add(atlas.layout.clone()),
+      };
+      store.atlases.insert(tag.clone(), (image, layout));
+      store.atlases_to_build.retain(|old| old.tag != tag);
+      store.atlases_to_build.push(atlas);
+    }
+    AssetType::Image => {
+      store.replace(tag, path.clone(), asset_server);
+      let old_image = old_path
+        .and_then(|old_path| asset_server.get_handle::<Image>(old_path));
+      if let Some(old_image) = old_image {
+        let image: Handle<Image> = asset_server.load(path);
+        swap_image(sprites, old_image.id(), &image);
+      }
+    }
+    AssetType::Sound | AssetType::Font => {
+      store.replace(tag, path, asset_server);
+    }
+  }
+  true
+}
+
+// Points sprites showing an image that has been replaced at the new one.
+fn swap_image(
+  sprites: &mut Query<&mut Sprite>,
+  old: AssetId<Image>,
+  new: &Handle<Image>,
+) {
+  for mut sprite in sprites.iter_mut() {
+    if sprite.image.id() == old {
+      sprite.image = new.clone();
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use bevy::asset::RenderAssetUsages;
+  use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat,
+  };
+
+  fn blank_image(width: u32, height: u32) -> Image {
+    Image::new_fill(
+      Extent3d { width, height, depth_or_array_layers: 1 },
+      TextureDimension::D2,
+      &[0, 0, 0, 255],
+      TextureFormat::Rgba8UnormSrgb,
+      RenderAssetUsages::default(),
+    )
+  }
+
+  #[test]
+  fn test_rebuild_resized_sheet() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Image>().init_asset::<TextureAtlasLayout>();
+    let sheet = AssetType::SpriteSheet {
+      tile_size: Vec2::splat(16.0),
+      sprites_x: 4,
+      sprites_y: 1,
+      padding: Vec2::ZERO,
+      offset: Vec2::ZERO,
+    };
+    let atlas = FutureAtlas::new("ship", &sheet).unwrap();
+    let world = app.world_mut();
+    let image =
+      world.resource_mut::<Assets<Image>>().add(blank_image(64, 16));
+    let layout = world
+      .resource_mut::<Assets<TextureAtlasLayout>>()
+      .add(atlas.layout.clone());
+    world.insert_resource(AssetStore {
+      asset_index: HashMap::new(),
+      atlases_to_build: vec![atlas],
+      atlases: HashMap::from_iter([
+        ("ship".to_string(), (image.clone(), layout.clone())),
+      ]),
+      fonts: Vec::new(),
+      groups: Vec::new(),
+      requests: Vec::new(),
+    });
+    app.add_systems(Update, rebuild_changed_atlases);
+    app.update();
+
+    // The artist adds a second row of sprites
+    app
+      .world_mut()
+      .resource_mut::<Assets<Image>>()
+      .insert(image.id(), blank_image(64, 32));
+    app.update();
+    let layouts = app.world().resource::<Assets<TextureAtlasLayout>>();
+    assert_eq!(layouts.get(&layout).unwrap().textures.len(), 8);
+    let store = app.world().resource::<AssetStore>();
+    assert_eq!(store.atlases_to_build[0].layout.textures.len(), 8);
+  }
+}
@@ -1,30 +1,58 @@
 //START: use
-use bevy::{prelude::*, asset::LoadedUntypedAsset};
+use bevy::{prelude::*, app::AppExit, asset::LoadState, log};
 use bevy::state::state::FreelyMutableState;
 use bevy_egui::EguiContexts;
-use crate::{AssetStore, egui::egui::Window, MenuResource, LoadedAssets, AssetManager};
-use crate::bevy_assets::setup_asset_store;
+use crate::{AssetStore, egui::egui::{Color32, Window}, MenuResource, LoadedAssets, AssetManager};
+use crate::egui::egui::{FontData, FontDefinitions, FontFamily};
+use std::sync::Arc;
+use crate::bevy_assets::{setup_asset_store, AssetGroupStates};
 //END: use
 
 //START: setup
+/// Sent by the loading menu when an asset fails to load, for example
+/// because the file is corrupt or no loader supports it. The loading
+/// menu lists failures and lets the player quit or continue without
+/// them; a game can also react to the event, for example by exiting or
+/// by loading a placeholder with [`AssetStore::replace`].
+#[derive(Event, Debug, Clone)]
+pub struct AssetLoadFailed {
+    /// The tag the asset was added with.
+    pub tag: String,
+    /// Why the asset failed to load.
+    pub error: String,
+}
+
 #[derive(Resource)]
-pub(crate) struct AssetsToLoad(Vec<Handle<LoadedUntypedAsset>>);
+pub(crate) struct AssetsToLoad {
+    pending: Vec<String>,
+    failed: Vec<AssetLoadFailed>,
+}
+
+impl AssetsToLoad {
+    pub(crate) fn new(pending: Vec<String>) -> Self {
+        Self { pending, failed: Vec::new() }
+    }
+}
 
 pub(crate) fn setup(
     assets: Option<Res<AssetStore>>,
     asset_manager: Option<Res<AssetManager>>,
     asset_server: Res<AssetServer>,
+    to_load: Option<Res<AssetsToLoad>>,
     mut commands: Commands,
 ) {
+    // Asset groups queue their own assets before entering the menu
+    if to_load.is_some() {
+        return;
+    }
     let assets = match assets {
         Some(assets) => assets.into_inner(),
         None => {
             &setup_asset_store(asset_manager.as_ref().unwrap(), &mut commands, &asset_server)
         }
     };
-    let assets_to_load: Vec<Handle<LoadedUntypedAsset>> = assets
-        .asset_index.values().cloned().collect();
-    commands.insert_resource(AssetsToLoad(assets_to_load));
+    let pending: Vec<String> = assets.asset_index.keys().cloned().collect();
+    commands.insert_resource(AssetsToLoad::new(pending));
 }
 //END: setup
 
@@ -35,33 +63,75 @@ pub(crate) fn run<T>(
     mut state: ResMut<NextState<T>>,
     mut egui_context: EguiContexts,
     menu_info: Res<MenuResource<T>>,
+    mut group_states: ResMut<AssetGroupStates<T>>,
     // START_HIGHLIGHT
     mut store: ResMut<AssetStore>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
     loaded_assets: Res<LoadedAssets>,
     // END_HIGHLIGHT
+    mut failures: EventWriter<AssetLoadFailed>,
+    mut exit: EventWriter<AppExit>,
 ) where T: States+FromWorld+FreelyMutableState,
 {
-    to_load.0.retain(|handle| {
-        match asset_server.get_load_state(handle.id()) {
-            Some(bevy::asset::LoadState::Loaded) => false,
+    let load_state = |tag: &str| {
+        store.asset_index.get(tag)
+            .and_then(|handle| asset_server.get_load_state(handle.id()))
+    };
+    // Failed assets that the game has replaced are waited for again
+    let AssetsToLoad { pending, failed } = &mut *to_load;
+    failed.retain(|failure| {
+        let retry = !matches!(load_state(&failure.tag), Some(LoadState::Failed(_)));
+        if retry {
+            pending.push(failure.tag.clone());
+        }
+        !retry
+    });
+    pending.retain(|tag| {
+        match load_state(tag) {
+            Some(LoadState::Loaded) => false,
+            Some(LoadState::Failed(error)) => {
+                let failure = AssetLoadFailed { tag: tag.clone(), error: error.to_string() };
+                log::error!("Unable to load asset {}: {}", failure.tag, failure.error);
+                failures.write(failure.clone());
+                failed.push(failure);
+                false
+            }
             _ => true,
         }
     });
+    let mut finished = to_load.pending.is_empty() && to_load.failed.is_empty();
+    Window::new("Loading, Please Wait").show(
+        egui_context.ctx_mut(), |ui| {
+            ui.label(
+                format!("{} assets remaining", to_load.pending.len())
+            );
+            if to_load.failed.is_empty() {
+                return;
+            }
+            ui.colored_label(Color32::RED, format!("{} assets failed to load:", to_load.failed.len()));
+            for failure in to_load.failed.iter() {
+                ui.colored_label(Color32::RED, format!("{}: {}", failure.tag, failure.error));
+            }
+            if to_load.pending.is_empty() {
+                ui.horizontal(|ui| {
+                    if ui.button("Continue Without Them").clicked() {
+                        finished = true;
+                    }
+                    if ui.button("Quit").clicked() {
+                        exit.write(AppExit::error());
+                    }
+                });
+            }
+        });
     //START: finished_loading
-    if to_load.0.is_empty() {
+    if finished {
         // START_HIGHLIGHT
         load_atlases(&mut store, &mut texture_atlases, &loaded_assets);
         // END_HIGHLIGHT
-        state.set(menu_info.menu_state.clone());
+        let next = group_states.resume.take();
+        state.set(next.unwrap_or(menu_info.menu_state.clone()));
     }
     //END: finished_loading
-    Window::new("Loading, Please Wait").show(
-        egui_context.ctx_mut(), |ui| {
-            ui.label(
-                format!("{} assets remaining", to_load.0.len())
-            )
-        });
 }
 
 //START: load_atlases
@@ -71,13 +141,16 @@ fn load_atlases(
     loaded_assets: &LoadedAssets,
 ) {
     for new_atlas in store.atlases_to_build.iter() {
-        let atlas = TextureAtlasLayout::from_grid(
-            new_atlas.tile_size.as_uvec2(),
-            new_atlas.sprites_x as u32,
-            new_atlas.sprites_y as u32,
-            None, None);
-        let atlas_handle = texture_atlases.add(atlas);
-        let img = store.get_handle(&new_atlas.texture_tag, loaded_assets).unwrap();
+        // Sheets from asset groups loaded earlier are already built
+        if store.atlases.contains_key(&new_atlas.tag) {
+            continue;
+        }
+        let atlas_handle = texture_atlases.add(new_atlas.layout.clone());
+        let Some(img) = store.get_handle(&new_atlas.texture_tag, loaded_assets) else {
+            // The image failed to load, and the player chose to continue
+            log::warn!("Skipping sprite sheet {}: its image isn't loaded", new_atlas.tag);
+            continue;
+        };
         store
             .atlases
             .insert(new_atlas.tag.clone(), (img, atlas_handle));
@@ -86,6 +159,37 @@ fn load_atlases(
 //END: load_atlases
 //END: run
 
+// Registers loaded fonts with egui, with the first font added as the
+// default for both proportional and monospace text. Each font is also
+// available as `FontFamily::Name(tag)`.
+pub(crate) fn register_fonts(
+    store: Res<AssetStore>,
+    loaded_assets: Res<LoadedAssets>,
+    fonts: Res<Assets<Font>>,
+    mut egui_context: EguiContexts,
+) {
+    if store.fonts.is_empty() {
+        return;
+    }
+    let mut definitions = FontDefinitions::default();
+    for tag in store.fonts.iter().rev() {
+        let font = store.get_handle::<Font>(tag, &loaded_assets)
+            .and_then(|handle| fonts.get(&handle));
+        let Some(font) = font else {
+            continue;
+        };
+        definitions.font_data.insert(
+            tag.clone(),
+            Arc::new(FontData::from_owned(font.data.to_vec()))
+        );
+        for family in [FontFamily::Proportional, FontFamily::Monospace] {
+            definitions.families.entry(family).or_default().insert(0, tag.clone());
+        }
+        definitions.families.insert(FontFamily::Name(tag.as_str().into()), vec![tag.clone()]);
+    }
+    egui_context.ctx_mut().set_fonts(definitions);
+}
+
 //START: end
 pub(crate) fn exit(
     mut commands: Commands,
@@ -0,0 +1,291 @@
+use crate::{AssetType, AtlasDescriptor};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// The formats an asset manifest can be written in. See
+/// [`AssetManager::add_manifest`](crate::AssetManager::add_manifest).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+  Ron,
+  Json,
+  Toml,
+}
+
+impl ManifestFormat {
+  /// Picks a format from a filename's extension: `.ron`, `.json` or
+  /// `.toml`.
+  pub fn from_filename(filename: &str) -> anyhow::Result<Self> {
+    let extension = std::path::Path::new(filename)
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .map(str::to_lowercase);
+    match extension.as_deref() {
+      Some("ron") => Ok(Self::Ron),
+      Some("json") => Ok(Self::Json),
+      Some("toml") => Ok(Self::Toml),
+      _ => Err(anyhow::Error::msg(format!(
+        "{filename} is not a .ron, .json or .toml asset manifest"
+      ))),
+    }
+  }
+}
+
+// A manifest is a list of assets, under an `assets` key (TOML can't
+// have a list at the top level).
+#[derive(Deserialize)]
+struct Manifest {
+  assets: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+  tag: String,
+  path: String,
+  #[serde(rename = "type")]
+  asset_type: ManifestAssetType,
+  tile_size: Option<[f32; 2]>,
+  sprites_x: Option<usize>,
+  sprites_y: Option<usize>,
+  padding: Option<[f32; 2]>,
+  offset: Option<[f32; 2]>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ManifestAssetType {
+  Image,
+  Sound,
+  SpriteSheet,
+  PackedSpriteSheet,
+  Font,
+}
+
+impl ManifestEntry {
+  // Returns the entry's filename and type. Packed sprite sheets are read
+  // with `read_descriptor`, and take their filename from it.
+  fn asset(
+    &self,
+    read_descriptor: impl Fn(&str) -> anyhow::Result<AtlasDescriptor>,
+  ) -> anyhow::Result<(String, AssetType)> {
+    if let ManifestAssetType::PackedSpriteSheet = self.asset_type {
+      let descriptor = read_descriptor(&self.path)?;
+      return Ok((
+        descriptor.image.clone(),
+        AssetType::PackedSpriteSheet(descriptor),
+      ));
+    }
+    Ok((self.path.clone(), self.asset_type()?))
+  }
+
+  fn asset_type(&self) -> anyhow::Result<AssetType> {
+    let vec2 = |value: Option<[f32; 2]>| Vec2::from(value.unwrap_or_default());
+    match self.asset_type {
+      ManifestAssetType::Image => Ok(AssetType::Image),
+      ManifestAssetType::Sound => Ok(AssetType::Sound),
+      ManifestAssetType::PackedSpriteSheet => Err(anyhow::Error::msg(
+        format!("{}: packed sprite sheets need a descriptor", self.tag),
+      )),
+      ManifestAssetType::Font => Ok(AssetType::Font),
+      ManifestAssetType::SpriteSheet => {
+        match (self.tile_size, self.sprites_x, self.sprites_y) {
+          (Some([width, height]), Some(sprites_x), Some(sprites_y)) => {
+            Ok(AssetType::SpriteSheet {
+              tile_size: Vec2::new(width, height),
+              sprites_x,
+              sprites_y,
+              padding: vec2(self.padding),
+              offset: vec2(self.offset),
+            })
+          }
+          _ => Err(anyhow::Error::msg(format!(
+            "{}: sprite sheets need tile_size, sprites_x and sprites_y",
+            self.tag
+          ))),
+        }
+      }
+    }
+  }
+}
+
+// Reads a manifest into (tag, filename, type) entries, using
+// `read_descriptor` to read packed sprite sheets' descriptors. Every bad
+// entry is reported in one error, rather than stopping at the first.
+pub(crate) fn parse_manifest(
+  source: &str,
+  format: ManifestFormat,
+  read_descriptor: impl Fn(&str) -> anyhow::Result<AtlasDescriptor>,
+) -> anyhow::Result<Vec<(String, String, AssetType)>> {
+  let manifest: Manifest = match format {
+    // Lets optional fields be written without `Some(...)`
+    ManifestFormat::Ron => ron::Options::default()
+      .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+      .from_str(source)?,
+    ManifestFormat::Json => serde_json::from_str(source)?,
+    ManifestFormat::Toml => toml::from_str(source)?,
+  };
+  let mut assets: Vec<(String, String, AssetType)> = Vec::new();
+  let mut errors = Vec::new();
+  for entry in manifest.assets {
+    if assets.iter().any(|(tag, _, _)| *tag == entry.tag) {
+      errors.push(format!("{}: tag is listed more than once", entry.tag));
+      continue;
+    }
+    match entry.asset(&read_descriptor) {
+      Ok((path, asset_type)) => assets.push((entry.tag, path, asset_type)),
+      Err(e) => errors.push(e.to_string()),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(anyhow::Error::msg(errors.join("\n")));
+  }
+  Ok(assets)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::AssetManager;
+
+  const RON: &str = r#"(assets: [
+    (tag: "ship", path: "ship.png", type: image),
+    (tag: "boom", path: "boom.ogg", type: sound),
+    (tag: "flames", path: "flames.png", type: sprite_sheet,
+      tile_size: (16, 24), sprites_x: 4, sprites_y: 1),
+    (tag: "hud", path: "hud.ttf", type: font),
+  ])"#;
+
+  const JSON: &str = r#"{"assets": [
+    {"tag": "ship", "path": "ship.png", "type": "image"},
+    {"tag": "boom", "path": "boom.ogg", "type": "sound"},
+    {"tag": "flames", "path": "flames.png", "type": "sprite_sheet",
+      "tile_size": [16, 24], "sprites_x": 4, "sprites_y": 1},
+    {"tag": "hud", "path": "hud.ttf", "type": "font"}
+  ]}"#;
+
+  const TOML: &str = r#"
+    [[assets]]
+    tag = "ship"
+    path = "ship.png"
+    type = "image"
+
+    [[assets]]
+    tag = "boom"
+    path = "boom.ogg"
+    type = "sound"
+
+    [[assets]]
+    tag = "flames"
+    path = "flames.png"
+    type = "sprite_sheet"
+    tile_size = [16, 24]
+    sprites_x = 4
+    sprites_y = 1
+
+    [[assets]]
+    tag = "hud"
+    path = "hud.ttf"
+    type = "font"
+  "#;
+
+  #[test]
+  fn test_formats() {
+    for (source, format) in [
+      (RON, ManifestFormat::Ron),
+      (JSON, ManifestFormat::Json),
+      (TOML, ManifestFormat::Toml),
+    ] {
+      let assets = parse_manifest(source, format, descriptor).unwrap();
+      let tags: Vec<&str> =
+        assets.iter().map(|(tag, _, _)| tag.as_str()).collect();
+      assert_eq!(tags, ["ship", "boom", "flames", "hud"], "{format:?}");
+      assert!(matches!(assets[0].2, AssetType::Image));
+      assert!(matches!(assets[1].2, AssetType::Sound));
+      assert!(matches!(
+        assets[2].2,
+        AssetType::SpriteSheet { tile_size, sprites_x: 4, sprites_y: 1,
+          padding: Vec2::ZERO, offset: Vec2::ZERO }
+          if tile_size == Vec2::new(16.0, 24.0)
+      ));
+      assert!(matches!(assets[3].2, AssetType::Font));
+    }
+  }
+
+  fn descriptor(path: &str) -> anyhow::Result<AtlasDescriptor> {
+    if path != "hero.json" {
+      return Err(anyhow::Error::msg(format!("{path} not found")));
+    }
+    Ok(AtlasDescriptor {
+      image: "hero.png".to_string(),
+      size: UVec2::new(16, 16),
+      frames: vec![("idle".to_string(), URect::new(0, 0, 16, 16))],
+    })
+  }
+
+  #[test]
+  fn test_sheet_options() {
+    let source = r#"(assets: [
+      (tag: "tiles", path: "tiles.png", type: sprite_sheet,
+        tile_size: (16, 16), sprites_x: 8, sprites_y: 8,
+        padding: (2, 2), offset: (1, 0)),
+      (tag: "hero", path: "hero.json", type: packed_sprite_sheet),
+    ])"#;
+    let assets =
+      parse_manifest(source, ManifestFormat::Ron, descriptor).unwrap();
+    assert!(matches!(
+      assets[0].2,
+      AssetType::SpriteSheet { padding, offset, .. }
+        if padding == Vec2::splat(2.0) && offset == Vec2::X
+    ));
+    assert_eq!(assets[1].1, "hero.png");
+    assert_eq!(
+      assets[1].2,
+      AssetType::PackedSpriteSheet(descriptor("hero.json").unwrap())
+    );
+
+    let missing = r#"(assets: [
+      (tag: "villain", path: "villain.json", type: packed_sprite_sheet),
+    ])"#;
+    let error = parse_manifest(missing, ManifestFormat::Ron, descriptor)
+      .unwrap_err()
+      .to_string();
+    assert!(error.contains("villain.json not found"), "{error}");
+  }
+
+  #[test]
+  fn test_from_filename() {
+    assert_eq!(
+      ManifestFormat::from_filename("assets.RON").unwrap(),
+      ManifestFormat::Ron
+    );
+    assert_eq!(
+      ManifestFormat::from_filename("levels/art.toml").unwrap(),
+      ManifestFormat::Toml
+    );
+    assert!(ManifestFormat::from_filename("assets.yaml").is_err());
+    assert!(ManifestFormat::from_filename("assets").is_err());
+  }
+
+  #[test]
+  fn test_reports_every_error() {
+    let source = r#"(assets: [
+      (tag: "a", path: "a.png", type: sprite_sheet, sprites_x: 2),
+      (tag: "b", path: "b.png", type: image),
+      (tag: "b", path: "c.png", type: image),
+    ])"#;
+    let error = parse_manifest(source, ManifestFormat::Ron, descriptor)
+      .unwrap_err()
+      .to_string();
+    assert!(error.contains("a: sprite sheets need"), "{error}");
+    assert!(error.contains("b: tag is listed more than once"), "{error}");
+
+    let error = AssetManager::new()
+      .add_manifest_str(RON, ManifestFormat::Ron)
+      .err()
+      .unwrap()
+      .to_string();
+    for filename in ["ship.png", "boom.ogg", "flames.png"] {
+      assert!(error.contains(filename), "{error}");
+    }
+  }
+}
@@ -2,21 +2,79 @@ mod asset_manager;
 pub use asset_manager::*;
 mod asset_store;
 pub use asset_store::*;
+mod asset_groups;
+pub(crate) use asset_groups::*;
+mod asset_requests;
+pub use asset_requests::AssetReady;
+pub(crate) use asset_requests::load_requests;
+mod atlas_descriptor;
+pub use atlas_descriptor::AtlasDescriptor;
+mod manifest;
+pub use manifest::ManifestFormat;
+pub(crate) use manifest::parse_manifest;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
+#[cfg(feature = "hot_reload")]
+pub(crate) use hot_reload::HotReloadPlugin;
+#[cfg(feature = "embedded_assets")]
+mod embedded;
+#[cfg(feature = "embedded_assets")]
+pub use embedded::use_embedded_assets;
+#[cfg(feature = "embedded_assets")]
+pub(crate) use embedded::*;
 mod loading_menu;
 pub(crate) use loading_menu::*;
+pub use loading_menu::AssetLoadFailed;
 
+/// Compiles the game's assets into the binary when the
+/// `embedded_assets` feature is enabled, and does nothing otherwise.
+/// Call it at the start of `main`. The game's build script must write
+/// the list of files to `$OUT_DIR/embedded_assets.rs`, as a slice of
+/// `(filename, include_bytes!(path))` pairs. Every file listed is
+/// compiled in, whether or not the `AssetManager` uses it.
+#[cfg(feature = "embedded_assets")]
+#[macro_export]
+macro_rules! embed_assets {
+  () => {
+    $crate::use_embedded_assets(include!(concat!(
+      env!("OUT_DIR"),
+      "/embedded_assets.rs"
+    )))
+  };
+}
+
+/// Compiles the game's assets into the binary when the
+/// `embedded_assets` feature is enabled, and does nothing otherwise.
+#[cfg(not(feature = "embedded_assets"))]
+#[macro_export]
+macro_rules! embed_assets {
+  () => {};
+}
+
+/// Spawns a sprite showing the image tagged `$index`, with a transform
+/// and any extra components. Evaluates to the new entity, or logs an
+/// error and evaluates to `None` if the image can't be found.
 #[macro_export]
 macro_rules! spawn_image {
  ($assets:expr, $commands:expr, $index:expr, $x:expr, $y:expr, $z:expr, 
    $loaded_assets:expr,
     $($component:expr),*) => 
 {
-    $commands.spawn((
-        Sprite::from_image($assets.get_handle($index, $loaded_assets).unwrap()),
-        Transform::from_xyz($x, $y, $z),
-    ))
-    $(
-      .insert($component)
-    )*
+    match $assets.try_get_handle($index, $loaded_assets) {
+      Ok(image) => Some(
+        $commands.spawn((
+          Sprite::from_image(image),
+          Transform::from_xyz($x, $y, $z),
+        ))
+        $(
+          .insert($component)
+        )*
+        .id()
+      ),
+      Err(e) => {
+        bevy::log::error!("Unable to spawn image: {e}");
+        None
+      }
+    }
  };
 }
\ No newline at end of file
@@ -10,9 +10,16 @@ pub enum AnimationOption {
 }
 //END: AnimationOption
 
+// A frame is picked by its index in the sprite sheet, or by name for
+// packed sheets that were loaded with named frames.
+enum SpriteFrame {
+    Index(usize),
+    Name(String),
+}
+
 //START: AnimationFrame
 pub struct AnimationFrame {
-    sprite_index: usize,//<callout id="animation_frame_sprite_index" />
+    sprite_index: SpriteFrame,//<callout id="animation_frame_sprite_index" />
     delay_ms: u128,//<callout id="animation_frame_delay" />
     action: Vec<AnimationOption>,//<callout id="animation_frame_options" />
 }
@@ -23,7 +30,17 @@ impl AnimationFrame {
                delay_ms: u128,
                action: Vec<AnimationOption>
     ) -> Self {
-        Self { sprite_index, delay_ms, action }
+        Self { sprite_index: SpriteFrame::Index(sprite_index), delay_ms, action }
+    }
+
+    /// Shows the frame called `name`, from a packed sprite sheet's
+    /// descriptor. Unknown names are logged, and the frame is skipped.
+    pub fn named<S: ToString>(
+        name: S,
+        delay_ms: u128,
+        action: Vec<AnimationOption>
+    ) -> Self {
+        Self { sprite_index: SpriteFrame::Name(name.to_string()), delay_ms, action }
     }
 }
 //END: AnimationFrame
@@ -127,9 +144,17 @@ pub fn cycle_animations(
                         }
                     }
                     if let Some(ta) = &mut sprite.texture_atlas {
-                        ta.index = cycle
+                        match &cycle
                             .frames[animation.current_frame]
-                            .sprite_index; // <callout id="cycle_animations_new_frame" />
+                            .sprite_index { // <callout id="cycle_animations_new_frame" />
+                            SpriteFrame::Index(index) => ta.index = *index,
+                            SpriteFrame::Name(name) => {
+                                match assets.frame_index_in_layout(&ta.layout, name) {
+                                    Ok(index) => ta.index = index,
+                                    Err(e) => log::warn!("{e}"),
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -147,18 +172,22 @@ macro_rules! spawn_animated_sprite {
    ($assets:expr, $commands:expr, $index:expr, $x:expr, $y:expr, $z:expr,
       $animation_name:expr, $($component:expr),*) => 
   {
-      let Some((img, atlas)) = $assets.get_atlas_handle($index) else { panic!() };
-      $commands.spawn((
-         Sprite::from_atlas_image(img.clone(), TextureAtlas {
-             layout: atlas.clone(),
-             index: 0,
-         }),
-         Transform::from_xyz($x, $y, $z),
-         AnimationCycle::new($animation_name),
-      ))
-      $(
-       .insert($component)
-      )*;
+      match $assets.try_get_atlas_handle($index) {
+        Ok((img, atlas)) => {
+          $commands.spawn((
+             Sprite::from_atlas_image(img.clone(), TextureAtlas {
+                 layout: atlas.clone(),
+                 index: 0,
+             }),
+             Transform::from_xyz($x, $y, $z),
+             AnimationCycle::new($animation_name),
+          ))
+          $(
+           .insert($component)
+          )*;
+        }
+        Err(e) => bevy::log::error!("Unable to spawn animated sprite: {e}"),
+      }
   }
 }
 //END: animation_macro
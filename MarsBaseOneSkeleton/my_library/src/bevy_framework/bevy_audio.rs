@@ -0,0 +1,397 @@
+use bevy::{log, prelude::*};
+use bevy::audio::{SpatialScale, Volume};
+use crate::{AssetError, AssetStore, LoadedAssets};
+
+/// The channels that sounds are mixed on. Each bus has its own volume,
+/// and can be muted on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+  Music,
+  Sfx,
+  Ui,
+}
+
+impl AudioBus {
+  fn index(self) -> usize {
+    self as usize
+  }
+}
+
+/// Volume and mute settings for each [`AudioBus`], and the background
+/// music. Insert one before adding `GameStatePlugin` to change the
+/// defaults; otherwise a default mixer is created.
+///
+/// ```
+/// use my_library_mbone_skeleton::{AudioBus, AudioMixer};
+/// let mut mixer = AudioMixer::new().with_sound_limit(3);
+/// mixer.set_volume(AudioBus::Music, 0.5);
+/// mixer.set_muted(AudioBus::Ui, true);
+/// assert_eq!(mixer.volume(AudioBus::Music), 0.5);
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct AudioMixer {
+  volumes: [f32; 3],
+  muted: [bool; 3],
+  sound_limit: usize,
+  crossfade_seconds: f32,
+  hearing_distance: f32,
+  music: Option<String>,
+}
+
+impl Default for AudioMixer {
+  fn default() -> Self {
+    Self {
+      volumes: [1.0; 3],
+      muted: [false; 3],
+      sound_limit: 4,
+      crossfade_seconds: 1.0,
+      hearing_distance: 100.0,
+      music: None,
+    }
+  }
+}
+
+impl AudioMixer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets how many copies of the same sound can play at once. Sounds
+  /// played beyond the limit are dropped. The default is 4.
+  pub fn with_sound_limit(mut self, limit: usize) -> Self {
+    self.sound_limit = limit;
+    self
+  }
+
+  /// Sets how long music takes to fade into the next track, in seconds.
+  /// Zero switches tracks immediately. The default is one second.
+  pub fn with_crossfade(mut self, seconds: f32) -> Self {
+    self.crossfade_seconds = seconds.max(0.0);
+    self
+  }
+
+  /// Sets how far from the camera, in pixels, sounds played with
+  /// [`AssetStore::play_at`] stay at full volume. Further away they
+  /// fade with the square of the distance. The default is 100.
+  pub fn with_hearing_distance(mut self, pixels: f32) -> Self {
+    self.hearing_distance = pixels.max(f32::EPSILON);
+    self
+  }
+
+  /// The volume of `bus`, from 0.0 (silent) to 1.0 (full).
+  pub fn volume(&self, bus: AudioBus) -> f32 {
+    self.volumes[bus.index()]
+  }
+
+  /// Sets the volume of `bus`, clamped to between 0.0 and 1.0. Sounds
+  /// that are already playing change too.
+  pub fn set_volume(&mut self, bus: AudioBus, volume: f32) {
+    self.volumes[bus.index()] = volume.clamp(0.0, 1.0);
+  }
+
+  pub fn is_muted(&self, bus: AudioBus) -> bool {
+    self.muted[bus.index()]
+  }
+
+  /// Mutes or unmutes `bus`, keeping its volume for when it's unmuted.
+  pub fn set_muted(&mut self, bus: AudioBus, muted: bool) {
+    self.muted[bus.index()] = muted;
+  }
+
+  pub fn toggle_muted(&mut self, bus: AudioBus) {
+    self.muted[bus.index()] = !self.muted[bus.index()];
+  }
+
+  /// Starts looping the sound tagged `tag` as background music,
+  /// crossfading from the current track. Playing the current track
+  /// again does nothing.
+  pub fn play_music<S: ToString>(&mut self, tag: S) {
+    self.music = Some(tag.to_string());
+  }
+
+  /// Fades out the background music.
+  pub fn stop_music(&mut self) {
+    self.music = None;
+  }
+
+  /// The tag of the background music, if any is playing.
+  pub fn music(&self) -> Option<&str> {
+    self.music.as_deref()
+  }
+
+  // The volume sounds on `bus` actually play at.
+  fn output_volume(&self, bus: AudioBus) -> f32 {
+    if self.is_muted(bus) {
+      0.0
+    } else {
+      self.volume(bus)
+    }
+  }
+}
+
+// A sound played through the mixer. Sounds over the limit are `dropped`:
+// silenced as they are spawned, and despawned afterwards.
+#[derive(Component)]
+pub(crate) struct MixerSound {
+  pub(crate) bus: AudioBus,
+  pub(crate) tag: String,
+  pub(crate) dropped: bool,
+}
+
+impl MixerSound {
+  fn new(bus: AudioBus, tag: &str) -> Self {
+    Self { bus, tag: tag.to_string(), dropped: false }
+  }
+}
+
+#[derive(Component)]
+pub(crate) struct MusicTrack {
+  tag: String,
+  fade: f32,
+  fading_out: bool,
+}
+
+impl AssetStore {
+  /// Plays the sound tagged `sound_name` once, on `bus`. Sounds over the
+  /// mixer's limit are dropped.
+  pub fn play_on(
+    &self,
+    sound_name: &str,
+    bus: AudioBus,
+    commands: &mut Commands,
+    assets: &LoadedAssets,
+  ) {
+    match self.try_get_handle::<AudioSource>(sound_name, assets) {
+      Ok(sound_handle) => {
+        commands.spawn((
+          AudioPlayer::new(sound_handle),
+          PlaybackSettings::DESPAWN,
+          MixerSound::new(bus, sound_name),
+        ));
+      }
+      Err(e) => log::error!("Unable to play sound: {e}"),
+    }
+  }
+
+  /// Plays a sound effect from `position` in the world. It is quieter
+  /// the further it is from the camera, and panned to the side it is
+  /// on (see [`AudioMixer::with_hearing_distance`]).
+  pub fn play_at(
+    &self,
+    sound_name: &str,
+    position: Vec2,
+    commands: &mut Commands,
+    assets: &LoadedAssets,
+  ) {
+    match self.try_get_handle::<AudioSource>(sound_name, assets) {
+      Ok(sound_handle) => {
+        commands.spawn((
+          AudioPlayer::new(sound_handle),
+          PlaybackSettings::DESPAWN.with_spatial(true),
+          Transform::from_translation(position.extend(0.0)),
+          MixerSound::new(AudioBus::Sfx, sound_name),
+        ));
+      }
+      Err(e) => log::error!("Unable to play sound: {e}"),
+    }
+  }
+}
+
+// Runs as each sound is spawned, before it starts playing: drops it if
+// too many copies are already playing, and applies its bus volume.
+pub(crate) fn mix_new_sound(
+  trigger: Trigger<OnAdd, MixerSound>,
+  mixer: Res<AudioMixer>,
+  mut sounds: Query<(&mut MixerSound, &mut PlaybackSettings)>,
+) {
+  let entity = trigger.target();
+  let Ok((sound, _)) = sounds.get(entity) else {
+    return;
+  };
+  let copies = sounds
+    .iter()
+    .filter(|(other, _)| !other.dropped && other.tag == sound.tag)
+    .count();
+  let volume = mixer.output_volume(sound.bus);
+  let Ok((mut sound, mut settings)) = sounds.get_mut(entity) else {
+    return;
+  };
+  if copies > mixer.sound_limit {
+    sound.dropped = true;
+    settings.volume = Volume::SILENT;
+    return;
+  }
+  settings.volume = Volume::Linear(volume);
+  if settings.spatial {
+    settings.spatial_scale =
+      Some(SpatialScale::new_2d(1.0 / mixer.hearing_distance));
+  }
+}
+
+pub(crate) fn despawn_dropped_sounds(
+  sounds: Query<(Entity, &MixerSound)>,
+  mut commands: Commands,
+) {
+  for (entity, sound) in sounds.iter() {
+    if sound.dropped {
+      commands.entity(entity).despawn();
+    }
+  }
+}
+
+// Applies volume changes to sounds that are already playing.
+pub(crate) fn apply_mixer(
+  mixer: Res<AudioMixer>,
+  mut sinks: Query<(&MixerSound, &mut AudioSink)>,
+  mut spatial_sinks: Query<(&MixerSound, &mut SpatialAudioSink)>,
+) {
+  if !mixer.is_changed() {
+    return;
+  }
+  for (sound, mut sink) in sinks.iter_mut() {
+    let volume = mixer.output_volume(sound.bus);
+    sink.set_volume(Volume::Linear(volume));
+  }
+  for (sound, mut sink) in spatial_sinks.iter_mut() {
+    let volume = mixer.output_volume(sound.bus);
+    sink.set_volume(Volume::Linear(volume));
+  }
+}
+
+// Starts the requested music, and crossfades between tracks.
+pub(crate) fn update_music(
+  mixer: Res<AudioMixer>,
+  store: Res<AssetStore>,
+  loaded_assets: Res<LoadedAssets>,
+  time: Res<Time>,
+  mut tracks: Query<(Entity, &mut MusicTrack, Option<&mut AudioSink>)>,
+  mut commands: Commands,
+  mut failed: Local<Option<String>>,
+) {
+  let playing = tracks
+    .iter()
+    .find(|(_, track, _)| !track.fading_out)
+    .map(|(_, track, _)| track.tag.clone());
+  if playing.as_deref() != mixer.music() {
+    for (_, mut track, _) in tracks.iter_mut() {
+      track.fading_out = true;
+    }
+    if let Some(tag) = mixer.music() {
+      match store.try_get_handle::<AudioSource>(tag, &loaded_assets) {
+        Ok(music) => {
+          commands.spawn((
+            AudioPlayer::new(music),
+            PlaybackSettings::LOOP.with_volume(Volume::SILENT),
+            MusicTrack { tag: tag.to_string(), fade: 0.0, fading_out: false },
+          ));
+        }
+        // Music from an asset group that is still loading starts later
+        Err(AssetError::NotLoaded(_)) => {}
+        Err(e) => {
+          if failed.as_deref() != Some(tag) {
+            log::error!("Unable to play music: {e}");
+            *failed = Some(tag.to_string());
+          }
+        }
+      }
+    }
+  }
+
+  let step = if mixer.crossfade_seconds > 0.0 {
+    time.delta_secs() / mixer.crossfade_seconds
+  } else {
+    1.0
+  };
+  let volume = mixer.output_volume(AudioBus::Music);
+  for (entity, mut track, sink) in tracks.iter_mut() {
+    track.fade = if track.fading_out {
+      (track.fade - step).max(0.0)
+    } else {
+      (track.fade + step).min(1.0)
+    };
+    if track.fading_out && track.fade <= 0.0 {
+      commands.entity(entity).despawn();
+      continue;
+    }
+    if let Some(mut sink) = sink {
+      sink.set_volume(Volume::Linear(track.fade * volume));
+    }
+  }
+}
+
+// Lets cameras hear sounds played with `AssetStore::play_at`.
+pub(crate) fn add_listeners(
+  mixer: Res<AudioMixer>,
+  cameras: Query<Entity, (With<Camera>, Without<SpatialListener>)>,
+  mut commands: Commands,
+) {
+  for camera in cameras.iter() {
+    commands
+      .entity(camera)
+      .insert(SpatialListener::new(mixer.hearing_distance));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use bevy::ecs::system::RunSystemOnce;
+
+  #[test]
+  fn test_volumes() {
+    let mut mixer = AudioMixer::new();
+    mixer.set_volume(AudioBus::Sfx, 1.5);
+    mixer.set_volume(AudioBus::Music, 0.25);
+    mixer.toggle_muted(AudioBus::Music);
+    assert_eq!(mixer.volume(AudioBus::Sfx), 1.0);
+    assert_eq!(mixer.output_volume(AudioBus::Music), 0.0);
+    mixer.toggle_muted(AudioBus::Music);
+    assert_eq!(mixer.output_volume(AudioBus::Music), 0.25);
+    for bus in [AudioBus::Music, AudioBus::Sfx, AudioBus::Ui] {
+      assert!(!mixer.is_muted(bus));
+    }
+
+    mixer.play_music("title");
+    assert_eq!(mixer.music(), Some("title"));
+    mixer.stop_music();
+    assert_eq!(mixer.music(), None);
+  }
+
+  #[test]
+  fn test_sound_limit() {
+    let mut world = World::new();
+    let mut mixer = AudioMixer::new().with_sound_limit(2);
+    mixer.set_volume(AudioBus::Sfx, 0.5);
+    world.insert_resource(mixer);
+    world.add_observer(mix_new_sound);
+    for _ in 0..5 {
+      world.spawn((
+        PlaybackSettings::DESPAWN,
+        MixerSound::new(AudioBus::Sfx, "boom"),
+      ));
+    }
+    world.spawn((
+      PlaybackSettings::DESPAWN,
+      MixerSound::new(AudioBus::Ui, "click"),
+    ));
+    world.run_system_once(despawn_dropped_sounds).unwrap();
+
+    let volumes = world
+      .run_system_once(|sounds: Query<(&MixerSound, &PlaybackSettings)>| {
+        sounds
+          .iter()
+          .map(|(sound, settings)| {
+            (sound.tag.clone(), settings.volume.to_linear())
+          })
+          .collect::<Vec<_>>()
+      })
+      .unwrap();
+    let booms: Vec<f32> = volumes
+      .iter()
+      .filter(|(tag, _)| tag == "boom")
+      .map(|(_, volume)| *volume)
+      .collect();
+    assert_eq!(booms, [0.5, 0.5]);
+    assert_eq!(volumes.len(), 3);
+  }
+}
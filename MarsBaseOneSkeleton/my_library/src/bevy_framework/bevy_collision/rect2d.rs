@@ -11,6 +11,21 @@ impl Rect2D {
     Self { min, max }
   }
 
+  pub fn min(&self) -> Vec2 {
+    self.min
+  }
+
+  pub fn max(&self) -> Vec2 {
+    self.max
+  }
+
+  pub fn contains(&self, point: Vec2) -> bool {
+    point.x >= self.min.x
+      && point.x <= self.max.x
+      && point.y >= self.min.y
+      && point.y <= self.max.y
+  }
+
   pub fn intersect(&self, other: &Self) -> bool {
     self.min.x <= other.max.x
       && self.max.x >= other.min.x
@@ -2,15 +2,130 @@ use crate::{AssetStore, LoadedAssets};
 
 //START: use
 use super::MenuResource;
-use bevy::{app::AppExit, prelude::*};
+use bevy::{app::AppExit, prelude::*, log};
+use bevy::ecs::system::SystemParam;
 use bevy::state::state::FreelyMutableState;
+use bevy_egui::{egui, EguiContexts};
 //END: use
 
+/// What a menu button, or its key, does.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuAction<T> {
+  /// Switches to another state, such as the game or another menu.
+  GoTo(T),
+  /// Closes the pause menu and resumes the game (see
+  /// [`GameStatePlugin::with_pause`](crate::GameStatePlugin::with_pause)).
+  Resume,
+  /// Exits the game.
+  Quit,
+}
+
+/// How a menu's buttons are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MenuStyle {
+  /// No buttons are shown; the background image shows the keys.
+  #[default]
+  KeysOnly,
+  /// Buttons in an egui window.
+  Egui,
+  /// Bevy UI buttons, in a column in the middle of the screen.
+  BevyUi,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MenuButton<T> {
+  label: String,
+  key: Option<KeyCode>,
+  action: MenuAction<T>,
+}
+
+/// Describes a menu screen for
+/// [`GameStatePlugin::with_menu`](crate::GameStatePlugin::with_menu): a
+/// background image, and buttons that can also be pressed with keys.
+///
+/// ```
+/// use bevy::prelude::*;
+/// use my_library_mbone_skeleton::{Menu, MenuAction, MenuStyle};
+///
+/// #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, States)]
+/// enum GamePhase { MainMenu, Options, Playing }
+///
+/// let menu = Menu::new()
+///   .with_background("main_menu")
+///   .with_style(MenuStyle::Egui)
+///   .with_button("Play", KeyCode::KeyP,
+///     MenuAction::GoTo(GamePhase::Playing))
+///   .with_button("Options", None,
+///     MenuAction::GoTo(GamePhase::Options))
+///   .with_button("Quit", KeyCode::KeyQ, MenuAction::Quit);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Menu<T> {
+  title: String,
+  background: Option<String>,
+  style: MenuStyle,
+  buttons: Vec<MenuButton<T>>,
+}
+
+impl<T> Default for Menu<T> {
+  fn default() -> Self {
+    Self {
+      title: String::new(),
+      background: None,
+      style: MenuStyle::default(),
+      buttons: Vec::new(),
+    }
+  }
+}
+
+impl<T> Menu<T> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Shows the image tagged `tag` behind the menu.
+  pub fn with_background<S: ToString>(mut self, tag: S) -> Self {
+    self.background = Some(tag.to_string());
+    self
+  }
+
+  /// Sets the title of the egui window the buttons are shown in.
+  pub fn with_title<S: ToString>(mut self, title: S) -> Self {
+    self.title = title.to_string();
+    self
+  }
+
+  pub fn with_style(mut self, style: MenuStyle) -> Self {
+    self.style = style;
+    self
+  }
+
+  /// Adds a button, which `key` (if any) also presses. Buttons are
+  /// shown in the order they are added.
+  pub fn with_button<S: ToString>(
+    mut self,
+    label: S,
+    key: impl Into<Option<KeyCode>>,
+    action: MenuAction<T>,
+  ) -> Self {
+    self.buttons.push(MenuButton {
+      label: label.to_string(),
+      key: key.into(),
+      action,
+    });
+    self
+  }
+}
+
 //START: tag
 #[derive(Component)]
 pub(crate) struct MenuElement;
 //END: tag
 
+// A Bevy UI menu button, by its index in the menu.
+#[derive(Component)]
+pub(crate) struct MenuButtonIndex(usize);
+
 //START: setup
 pub(crate) fn setup<T>(
   state: Res<State<T>>,
@@ -25,53 +140,214 @@ pub(crate) fn setup<T>(
 {
   let current_state = state.get();
   //START_HIGHLIGHT
-  let menu_graphic = {
-    if menu_resource.menu_state == *current_state {
-      assets.get_handle("main_menu", &loaded_assets).unwrap()
-    } else if menu_resource.game_end_state == *current_state {
-      assets.get_handle("game_over", &loaded_assets).unwrap()
-    } else {
-      panic!("Unknown menu state")
-    }
+  let Some(menu) = menu_resource.menu(current_state) else {
+    log::error!("No menu for state {current_state:?}");
+    return;
   };
   //END_HIGHLIGHT
 
   commands
       .spawn(Camera2d::default())
       .insert(MenuElement);
-  commands
-      .spawn((
-        Sprite::from_image(menu_graphic.clone()),
-        Transform::from_xyz(0.0, 0.0, 1.0),
-        MenuElement
-      ));
+  // Without its graphic the menu still works, it's just blank
+  if let Some(background) = &menu.background {
+    match assets.try_get_handle(background, &loaded_assets) {
+      Ok(menu_graphic) => {
+        commands
+            .spawn((
+              Sprite::from_image(menu_graphic),
+              Transform::from_xyz(0.0, 0.0, 1.0),
+              MenuElement
+            ));
+      }
+      Err(e) => log::error!("Unable to show menu: {e}"),
+    }
+  }
+
+  if menu.style == MenuStyle::BevyUi {
+    commands
+        .spawn((menu_node(), MenuElement))
+        .with_children(|parent| spawn_buttons(parent, menu));
+  }
 }
 //END: setup
 
+// Spawns a menu over the paused game, tagged with `tag`. The game's
+// camera shows it, so the background is a UI image rather than a sprite.
+pub(crate) fn spawn_overlay<T>(
+  commands: &mut Commands,
+  menu: &Menu<T>,
+  assets: &AssetStore,
+  loaded_assets: &LoadedAssets,
+  tag: impl Component,
+) {
+  let mut overlay = commands.spawn((menu_node(), tag));
+  if let Some(background) = &menu.background {
+    match assets.try_get_handle(background, loaded_assets) {
+      Ok(menu_graphic) => {
+        overlay.insert(ImageNode::new(menu_graphic));
+      }
+      Err(e) => log::error!("Unable to show menu: {e}"),
+    }
+  }
+  if menu.style == MenuStyle::BevyUi {
+    overlay.with_children(|parent| spawn_buttons(parent, menu));
+  }
+}
+
+fn menu_node() -> Node {
+  Node {
+    width: Val::Percent(100.0),
+    height: Val::Percent(100.0),
+    flex_direction: FlexDirection::Column,
+    align_items: AlignItems::Center,
+    justify_content: JustifyContent::Center,
+    row_gap: Val::Px(12.0),
+    ..default()
+  }
+}
+
+fn spawn_buttons<T>(parent: &mut ChildSpawnerCommands, menu: &Menu<T>) {
+  for (index, button) in menu.buttons.iter().enumerate() {
+    parent
+        .spawn((
+          Button,
+          Node {
+            padding: UiRect::axes(Val::Px(24.0), Val::Px(8.0)),
+            ..default()
+          },
+          BackgroundColor(Color::srgb(0.15, 0.15, 0.25)),
+          MenuButtonIndex(index),
+        ))
+        .with_child(Text::new(button.label.clone()));
+  }
+}
+
 //START: run
 pub(crate) fn run<T>(
-  keyboard: Res<ButtonInput<KeyCode>>,
   mut exit: EventWriter<AppExit>,
   current_state: Res<State<T>>,
   mut state: ResMut<NextState<T>>,
   menu_state: Res<MenuResource<T>>,
+  mut input: MenuInput,
 ) where
     T: States+FromWorld+FreelyMutableState,
 {
-  let current_state = current_state.get().clone();
-  if current_state == menu_state.menu_state {
-    if keyboard.just_pressed(KeyCode::KeyP) {
-      state.set(menu_state.game_start_state.clone());
-    } else if keyboard.just_pressed(KeyCode::KeyQ) {
+  let Some(menu) = menu_state.menu(current_state.get()) else {
+    return;
+  };
+  match input.pressed(menu) {
+    Some(MenuAction::GoTo(next)) => state.set(next.clone()),
+    Some(MenuAction::Quit) => {
       exit.write(AppExit::Success);
     }
+    // There's no game to resume from a menu state
+    Some(MenuAction::Resume) | None => {}
   }
-  else if current_state == menu_state.game_end_state {
-    if keyboard.just_pressed(KeyCode::KeyM) {
-      state.set(menu_state.menu_state.clone());
-    } else if keyboard.just_pressed(KeyCode::KeyQ) {
-      exit.write(AppExit::Success);
+}
+//END: run
+
+// The ways a menu's buttons can be pressed.
+#[derive(SystemParam)]
+pub(crate) struct MenuInput<'w, 's> {
+  keyboard: Res<'w, ButtonInput<KeyCode>>,
+  egui_context: EguiContexts<'w, 's>,
+  buttons: Query<
+    'w,
+    's,
+    (&'static Interaction, &'static MenuButtonIndex),
+    Changed<Interaction>,
+  >,
+}
+
+impl MenuInput<'_, '_> {
+  // The action of the button pressed this frame, by key or by clicking
+  // it in the menu's style.
+  pub(crate) fn pressed<'a, T>(
+    &mut self,
+    menu: &'a Menu<T>,
+  ) -> Option<&'a MenuAction<T>> {
+    let mut pressed = menu.buttons.iter().position(|button| {
+      button.key.is_some_and(|key| self.keyboard.just_pressed(key))
+    });
+    match menu.style {
+      MenuStyle::KeysOnly => {}
+      MenuStyle::Egui => {
+        egui::Window::new(menu.title.as_str())
+            .title_bar(!menu.title.is_empty())
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(self.egui_context.ctx_mut(), |ui| {
+              ui.vertical_centered(|ui| {
+                for (index, button) in menu.buttons.iter().enumerate() {
+                  if ui.button(button.label.as_str()).clicked() {
+                    pressed = Some(index);
+                  }
+                }
+              });
+            });
+      }
+      MenuStyle::BevyUi => {
+        for (interaction, index) in self.buttons.iter() {
+          if *interaction == Interaction::Pressed {
+            pressed = Some(index.0);
+          }
+        }
+      }
     }
+    pressed.map(|index| &menu.buttons[index].action)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::GameStatePlugin;
+
+  #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
+  enum Phase {
+    #[default]
+    Loading,
+    MainMenu,
+    Credits,
+    Playing,
+    GameOver,
+  }
+
+  #[test]
+  fn test_with_menu() {
+    let plugin = GameStatePlugin::new(
+      Phase::MainMenu,
+      Phase::Playing,
+      Phase::GameOver,
+    )
+    .with_menu(
+      Phase::MainMenu,
+      Menu::new()
+        .with_style(MenuStyle::Egui)
+        .with_button("Credits", None, MenuAction::GoTo(Phase::Credits)),
+    )
+    .with_menu(
+      Phase::Credits,
+      Menu::new().with_button("Back", KeyCode::Escape,
+        MenuAction::GoTo(Phase::MainMenu)),
+    );
+    let menus = MenuResource { menu_state: Phase::MainMenu, menus: plugin.menus };
+
+    let main_menu = menus.menu(&Phase::MainMenu).unwrap();
+    assert_eq!(main_menu.background, None);
+    assert_eq!(main_menu.buttons.len(), 1);
+    assert_eq!(main_menu.buttons[0].key, None);
+    let game_over = menus.menu(&Phase::GameOver).unwrap();
+    assert_eq!(game_over.background.as_deref(), Some("game_over"));
+    assert_eq!(game_over.buttons[0].key, Some(KeyCode::KeyM));
+    assert_eq!(
+      game_over.buttons[0].action,
+      MenuAction::GoTo(Phase::MainMenu)
+    );
+    assert!(menus.menu(&Phase::Credits).is_some());
+    assert!(menus.menu(&Phase::Playing).is_none());
+    assert!(menus.menu(&Phase::Loading).is_none());
   }
 }
-//END: run
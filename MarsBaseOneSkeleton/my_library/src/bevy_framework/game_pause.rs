@@ -0,0 +1,235 @@
+// Pausing the game. Pausing doesn't leave the game's state, so its
+// `OnExit` systems (such as `cleanup`) don't run and the game world stays
+// as it was. The systems `add_phase!` runs in the state stop, and virtual
+// time stops too, which freezes everything else driven by `Time`:
+// `cycle_animations`, `continual_parallax` and the game's own timers.
+use super::game_menus::{self, Menu, MenuAction, MenuInput};
+use crate::{AssetStore, LoadedAssets};
+use bevy::state::state::FreelyMutableState;
+use bevy::{app::AppExit, prelude::*};
+
+/// Whether the game is paused, with
+/// [`GameStatePlugin::with_pause`](crate::GameStatePlugin::with_pause).
+/// The `run` systems of [`add_phase!`](crate::add_phase) don't run while
+/// the game is paused; add [`game_running`] to other systems that should
+/// stop too.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
+pub enum PauseState {
+  #[default]
+  Running,
+  Paused,
+}
+
+/// A run condition that is true unless the game is paused. Apps without
+/// a [`PauseState`] are never paused.
+pub fn game_running(pause: Option<Res<State<PauseState>>>) -> bool {
+  pause.is_none_or(|pause| *pause.get() == PauseState::Running)
+}
+
+#[derive(Resource)]
+pub(crate) struct PauseResource<T> {
+  pub(crate) pauses: Vec<(T, KeyCode, Menu<T>)>,
+}
+
+impl<T: States> PauseResource<T> {
+  fn pause(&self, state: &T) -> Option<(KeyCode, &Menu<T>)> {
+    self
+      .pauses
+      .iter()
+      .find(|(pause_state, _, _)| pause_state == state)
+      .map(|(_, key, menu)| (*key, menu))
+  }
+}
+
+#[derive(Component)]
+pub(crate) struct PauseElement;
+
+// Pauses or resumes the game when the state's pause key is pressed.
+pub(crate) fn toggle_pause<T>(
+  keyboard: Res<ButtonInput<KeyCode>>,
+  state: Res<State<T>>,
+  pause_state: Res<State<PauseState>>,
+  mut next_pause: ResMut<NextState<PauseState>>,
+  pauses: Res<PauseResource<T>>,
+) where
+  T: States,
+{
+  let Some((key, _)) = pauses.pause(state.get()) else {
+    return;
+  };
+  if keyboard.just_pressed(key) {
+    next_pause.set(match pause_state.get() {
+      PauseState::Running => PauseState::Paused,
+      PauseState::Paused => PauseState::Running,
+    });
+  }
+}
+
+pub(crate) fn stop_time(mut time: ResMut<Time<Virtual>>) {
+  time.pause();
+}
+
+pub(crate) fn start_time(mut time: ResMut<Time<Virtual>>) {
+  time.unpause();
+}
+
+// Leaving a state, for example from the pause menu, ends its pause.
+pub(crate) fn end_pause(mut next_pause: ResMut<NextState<PauseState>>) {
+  next_pause.set(PauseState::Running);
+}
+
+pub(crate) fn setup<T>(
+  mut commands: Commands,
+  state: Res<State<T>>,
+  pauses: Res<PauseResource<T>>,
+  assets: Res<AssetStore>,
+  loaded_assets: Res<LoadedAssets>,
+) where
+  T: States,
+{
+  if let Some((_, menu)) = pauses.pause(state.get()) {
+    game_menus::spawn_overlay(
+      &mut commands,
+      menu,
+      &assets,
+      &loaded_assets,
+      PauseElement,
+    );
+  }
+}
+
+pub(crate) fn run<T>(
+  mut exit: EventWriter<AppExit>,
+  current_state: Res<State<T>>,
+  mut state: ResMut<NextState<T>>,
+  mut next_pause: ResMut<NextState<PauseState>>,
+  pauses: Res<PauseResource<T>>,
+  mut input: MenuInput,
+) where
+  T: States+FreelyMutableState,
+{
+  let Some((_, menu)) = pauses.pause(current_state.get()) else {
+    return;
+  };
+  match input.pressed(menu) {
+    Some(MenuAction::Resume) => next_pause.set(PauseState::Running),
+    Some(MenuAction::GoTo(next)) => {
+      state.set(next.clone());
+      next_pause.set(PauseState::Running);
+    }
+    Some(MenuAction::Quit) => {
+      exit.write(AppExit::Success);
+    }
+    None => {}
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::cleanup;
+  use bevy::state::app::StatesPlugin;
+
+  #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
+  enum Phase {
+    #[default]
+    Playing,
+    MainMenu,
+  }
+
+  #[derive(Component)]
+  struct GameElement;
+
+  #[derive(Component)]
+  struct Counter(u32);
+
+  fn count(mut counters: Query<&mut Counter>) {
+    counters.iter_mut().for_each(|mut counter| counter.0 += 1);
+  }
+
+  fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin));
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.init_state::<Phase>();
+    app.init_state::<PauseState>();
+    app.insert_resource(PauseResource {
+      pauses: vec![(Phase::Playing, KeyCode::Escape, Menu::new())],
+    });
+    app.add_systems(Update, toggle_pause::<Phase>);
+    app.add_systems(OnEnter(PauseState::Paused), stop_time);
+    app.add_systems(OnExit(PauseState::Paused), start_time);
+    app
+  }
+
+  fn press(app: &mut App, key: KeyCode) {
+    let mut keyboard = app.world_mut().resource_mut::<ButtonInput<_>>();
+    keyboard.release_all();
+    keyboard.clear();
+    keyboard.press(key);
+    app.update();
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+    app.update();
+  }
+
+  #[test]
+  fn test_pause_keeps_world() {
+    let mut app = app();
+    app.add_systems(
+      OnExit(Phase::Playing),
+      (cleanup::<GameElement>, end_pause),
+    );
+    app.world_mut().spawn(GameElement);
+    app.update();
+
+    press(&mut app, KeyCode::Escape);
+    let pause_state = app.world().resource::<State<PauseState>>();
+    assert_eq!(*pause_state.get(), PauseState::Paused);
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+    let mut elements = app.world_mut().query::<&GameElement>();
+    assert_eq!(elements.iter(app.world()).count(), 1);
+
+    press(&mut app, KeyCode::Escape);
+    let pause_state = app.world().resource::<State<PauseState>>();
+    assert_eq!(*pause_state.get(), PauseState::Running);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+
+    // Leaving the game from the pause menu cleans up, and unpauses
+    press(&mut app, KeyCode::Escape);
+    app
+      .world_mut()
+      .resource_mut::<NextState<Phase>>()
+      .set(Phase::MainMenu);
+    app.update();
+    app.update();
+    let mut elements = app.world_mut().query::<&GameElement>();
+    assert_eq!(elements.iter(app.world()).count(), 0);
+    let pause_state = app.world().resource::<State<PauseState>>();
+    assert_eq!(*pause_state.get(), PauseState::Running);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+  }
+
+  #[test]
+  fn test_pause_stops_phase_systems() {
+    let mut app = app();
+    crate::add_phase!(app, Phase, Phase::Playing,
+      start => [ ],
+      run => [ count ],
+      exit => [ ]
+    );
+    let counter = app.world_mut().spawn(Counter(0)).id();
+    let count = |app: &App| app.world().get::<Counter>(counter).unwrap().0;
+    app.update();
+    assert_eq!(count(&app), 1);
+
+    press(&mut app, KeyCode::Escape);
+    let paused = count(&app);
+    (0..10).for_each(|_| app.update());
+    assert_eq!(count(&app), paused);
+
+    press(&mut app, KeyCode::Escape);
+    let resumed = count(&app);
+    app.update();
+    assert_eq!(count(&app), resumed + 1);
+  }
+}
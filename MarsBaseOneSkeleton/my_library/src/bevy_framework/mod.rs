@@ -4,6 +4,11 @@ use bevy::prelude::*;
 use bevy::state::state::FreelyMutableState;
 
 mod game_menus;
+pub use game_menus::{Menu, MenuAction, MenuStyle};
+mod game_pause;
+pub use game_pause::{game_running, PauseState};
+mod bevy_audio;
+pub use bevy_audio::{AudioBus, AudioMixer};
 mod bevy_physics;
 pub use bevy_physics::*;
 mod bevy_collision;
@@ -11,8 +16,10 @@ pub use bevy_collision::*;
 
 pub struct GameStatePlugin<T> {
   menu_state: T,
-  game_start_state: T,
-  game_end_state: T,
+  asset_groups: Vec<(T, String)>,
+  music: Vec<(T, String)>,
+  menus: Vec<(T, Menu<T>)>,
+  pauses: Vec<(T, KeyCode, Menu<T>)>,
 }
 
 impl<T> GameStatePlugin<T>
@@ -21,7 +28,57 @@ impl<T> GameStatePlugin<T>
 {
   #[allow(clippy::new_without_default)]
   pub fn new(menu_state: T, game_start_state: T, game_end_state: T) -> Self {
-    Self { menu_state, game_start_state, game_end_state } //<callout id="generic_state.assign_playing" />
+    // The default menus show a splash image, and are driven by keys
+    let menus = vec![
+      (menu_state.clone(), Menu::new()
+        .with_background("main_menu")
+        .with_button("Play", KeyCode::KeyP, MenuAction::GoTo(game_start_state))
+        .with_button("Quit", KeyCode::KeyQ, MenuAction::Quit)),
+      (game_end_state, Menu::new()
+        .with_background("game_over")
+        .with_button("Main Menu", KeyCode::KeyM, MenuAction::GoTo(menu_state.clone()))
+        .with_button("Quit", KeyCode::KeyQ, MenuAction::Quit)),
+    ];
+    Self { menu_state, asset_groups: Vec::new(), music: Vec::new(), menus, pauses: Vec::new() } //<callout id="generic_state.assign_playing" />
+  }
+
+  /// Shows `menu` while in `state`, replacing the state's menu if it
+  /// already has one. This can change the main menu and game over
+  /// screens, or add menus in other states, such as options or credits.
+  pub fn with_menu(mut self, state: T, menu: Menu<T>) -> Self {
+    self.menus.retain(|(menu_state, _)| *menu_state != state);
+    self.menus.push((state, menu));
+    self
+  }
+
+  /// Lets the game be paused in `state` by pressing `key`, showing
+  /// `menu` over it. Pressing `key` again, or a button with
+  /// [`MenuAction::Resume`], carries on where the game left off. While
+  /// paused, `Time` and the state's `add_phase!` systems stand still, and
+  /// the game stays in `state`, so its world isn't cleaned up (see
+  /// [`PauseState`]).
+  pub fn with_pause(mut self, state: T, key: KeyCode, menu: Menu<T>) -> Self {
+    self.pauses.retain(|(pause_state, _, _)| *pause_state != state);
+    self.pauses.push((state, key, menu));
+    self
+  }
+
+  /// Loads the asset group called `group` (see
+  /// [`AssetManager::add_group`](crate::AssetManager::add_group)) when
+  /// entering `state`, showing the loading menu until it is ready. The
+  /// group is released when leaving for a state that doesn't use it. A
+  /// state can use several groups, and a group several states.
+  pub fn with_asset_group<S: ToString>(mut self, state: T, group: S) -> Self {
+    self.asset_groups.push((state, group.to_string()));
+    self
+  }
+
+  /// Loops the sound tagged `music` while in `state`, crossfading from
+  /// the previous state's music (see [`AudioMixer`]). States without
+  /// music keep playing whatever was playing before.
+  pub fn with_music<S: ToString>(mut self, state: T, music: S) -> Self {
+    self.music.push((state, music.to_string()));
+    self
   }
 }
 
@@ -38,23 +95,72 @@ impl<T> Plugin for GameStatePlugin<T>
     //END_HIGHLIGHT
     let start = MenuResource {
       menu_state: self.menu_state,
-      game_start_state: self.game_start_state,
-      game_end_state: self.game_end_state,
+      menus: self.menus.clone(),
     };
     app.insert_resource(start);
     //END: run_loader
 
-    app.add_systems(OnEnter(self.menu_state), game_menus::setup::<T>);
-    app.add_systems(Update, game_menus::run::<T>.run_if(in_state(self.menu_state)));
-    app.add_systems(OnExit(self.menu_state), cleanup::<game_menus::MenuElement>);
+    for (menu_state, _) in self.menus.iter() {
+      app.add_systems(OnEnter(*menu_state), game_menus::setup::<T>);
+      app.add_systems(Update, game_menus::run::<T>.run_if(in_state(*menu_state)));
+      app.add_systems(OnExit(*menu_state), cleanup::<game_menus::MenuElement>);
+    }
 
-    app.add_systems(OnEnter(self.game_end_state), game_menus::setup::<T>);
-    app.add_systems(Update, game_menus::run::<T>.run_if(in_state(self.game_end_state)));
-    app.add_systems(OnExit(self.game_end_state), cleanup::<game_menus::MenuElement>);
+    app.init_state::<PauseState>();
+    app.insert_resource(game_pause::PauseResource {
+      pauses: self.pauses.clone(),
+    });
+    app.add_systems(Update, game_pause::toggle_pause::<T>);
+    app.add_systems(OnEnter(PauseState::Paused), (
+      game_pause::stop_time,
+      game_pause::setup::<T>,
+    ));
+    app.add_systems(Update, game_pause::run::<T>
+      .run_if(in_state(PauseState::Paused)));
+    app.add_systems(OnExit(PauseState::Paused), (
+      game_pause::start_time,
+      cleanup::<game_pause::PauseElement>,
+    ));
+    for (state, _, _) in self.pauses.iter() {
+      app.add_systems(OnExit(*state), game_pause::end_pause);
+    }
 
+    app.add_event::<crate::AssetLoadFailed>();
     app.add_systems(OnEnter(T::default()), crate::bevy_assets::setup);
     app.add_systems(Update, crate::bevy_assets::run::<T>.run_if(in_state(T::default())));
+    app.add_systems(OnExit(T::default()), crate::bevy_assets::register_fonts);
     app.add_systems(OnExit(T::default()), crate::bevy_assets::exit);
+    app.add_event::<crate::AssetReady>();
+    app.add_systems(Update, crate::bevy_assets::load_requests
+      .run_if(resource_exists::<crate::AssetStore>));
+
+    app.add_systems(PreUpdate, crate::bevy_assets::load_groups::<T>);
+    let mut group_states: Vec<T> = Vec::new();
+    for (state, _) in self.asset_groups.iter() {
+      if !group_states.contains(state) {
+        group_states.push(*state);
+        app.add_systems(OnExit(*state), crate::bevy_assets::release_groups::<T>);
+      }
+    }
+    app.insert_resource(crate::bevy_assets::AssetGroupStates {
+      groups: self.asset_groups.clone(),
+      resume: None,
+    });
+
+    app.init_resource::<AudioMixer>();
+    app.add_observer(bevy_audio::mix_new_sound);
+    app.add_systems(Update, (
+      bevy_audio::apply_mixer,
+      bevy_audio::despawn_dropped_sounds,
+      bevy_audio::add_listeners,
+      bevy_audio::update_music.run_if(resource_exists::<crate::AssetStore>),
+    ));
+    for (state, music) in self.music.iter() {
+      let music = music.clone();
+      app.add_systems(OnEnter(*state), move |mut mixer: ResMut<AudioMixer>| {
+        mixer.play_music(&music);
+      });
+    }
   }
 }
 //END: build
@@ -63,11 +169,23 @@ impl<T> Plugin for GameStatePlugin<T>
 #[derive(Resource)]
 pub(crate) struct MenuResource<T> {
   pub(crate) menu_state: T,
-  pub(crate) game_start_state: T,
-  pub(crate) game_end_state: T,
+  pub(crate) menus: Vec<(T, Menu<T>)>,
 }
 //END: menu_resource
 
+impl<T: States> MenuResource<T> {
+  pub(crate) fn menu(&self, state: &T) -> Option<&Menu<T>> {
+    self
+      .menus
+      .iter()
+      .find(|(menu_state, _)| menu_state == state)
+      .map(|(_, menu)| menu)
+  }
+}
+
+/// Despawns every entity with the component `T`, usually when leaving
+/// a state. Pausing doesn't leave the game's state, so this doesn't run
+/// when the game is paused.
 pub fn cleanup<T>(query: Query<Entity, With<T>>, mut commands: Commands)
   where
       T: Component,
@@ -87,9 +205,10 @@ macro_rules! add_phase {
     $($app.add_systems(
       bevy::prelude::OnEnter::<$type>($phase),
       $start
-    ))*;
+    );)*
     $($app.add_systems(
-      bevy::prelude::Update, $run.run_if(in_state($phase))
+      bevy::prelude::Update,
+      $run.run_if(in_state($phase)).run_if($crate::game_running)
     );)*
     $($app.add_systems(
       bevy::prelude::OnExit::<$type>($phase),
@@ -0,0 +1,354 @@
+use rand::Rng;
+
+// Guard rails against expressions that would take forever to evaluate.
+const MAX_DICE: u32 = 1_000;
+const MAX_SIDES: u32 = 10_000;
+const MAX_EXPLOSIONS: u32 = 100;
+
+/// A single die rolled while evaluating a [`DiceExpression`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieRoll {
+  /// The number of sides on the die.
+  pub sides: u32,
+  /// The face that came up.
+  pub value: u32,
+  /// `false` if a keep-highest/keep-lowest modifier discarded the die.
+  pub kept: bool,
+  /// `true` if the die is subtracted from the total (the `1d4` in
+  /// `2d10-1d4`).
+  pub negative: bool,
+  /// `true` if the die was added because the previous die exploded.
+  pub exploded: bool,
+}
+
+/// The result of rolling a [`DiceExpression`]: the total, and every die
+/// that was rolled to reach it (in the order they were rolled).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceRoll {
+  /// The total, which stops at `i32::MAX` (or `i32::MIN`) rather than
+  /// overflowing.
+  pub total: i32,
+  pub rolls: Vec<DieRoll>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keep {
+  All,
+  Highest(u32),
+  Lowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DiceTerm {
+  Dice {
+    count: u32,
+    sides: u32,
+    explode: bool,
+    keep: Keep,
+    negative: bool,
+  },
+  Constant(i32),
+}
+
+/// A parsed dice-notation expression, such as `3d6+2`, `4d6kh3`,
+/// `2d10-1d4` or `1d6!`.
+///
+/// The supported notation is:
+///
+/// * `NdS` - roll `N` dice with `S` sides. `N` defaults to 1, and `d%`
+///   is shorthand for `d100`.
+/// * `!` after a dice term makes the dice explode: every maximum roll
+///   adds another die.
+/// * `khN` / `klN` after a dice term keeps only the highest/lowest `N`
+///   dice.
+/// * Terms (dice or plain numbers) are combined with `+` and `-`.
+///
+/// Parse an expression once with [`DiceExpression::parse`] if you roll
+/// it repeatedly, or use `RandomNumberGenerator::roll_dice` to parse
+/// and roll in one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpression {
+  terms: Vec<DiceTerm>,
+}
+
+impl DiceExpression {
+  /// Parses a dice-notation expression, returning an error describing
+  /// the problem if it isn't valid.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use my_library_mbone_skeleton::DiceExpression;
+  /// assert!(DiceExpression::parse("4d6kh3 + 2").is_ok());
+  /// assert!(DiceExpression::parse("4d").is_err());
+  /// ```
+  pub fn parse(expression: &str) -> anyhow::Result<Self> {
+    let chars: Vec<char> = expression
+      .chars()
+      .filter(|c| !c.is_whitespace())
+      .map(|c| c.to_ascii_lowercase())
+      .collect();
+    if chars.is_empty() {
+      anyhow::bail!("empty dice expression");
+    }
+    let mut parser = Parser {
+      source: expression,
+      chars,
+      pos: 0,
+    };
+
+    let mut terms = Vec::new();
+    let mut negative = parser.eat('-');
+    if !negative {
+      parser.eat('+');
+    }
+    loop {
+      terms.push(parser.term(negative)?);
+      if parser.eat('+') {
+        negative = false;
+      } else if parser.eat('-') {
+        negative = true;
+      } else if parser.at_end() {
+        break;
+      } else {
+        return Err(parser.error("expected '+' or '-'"));
+      }
+    }
+    Ok(Self { terms })
+  }
+
+  /// Rolls the expression using any `rand` generator.
+  pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceRoll {
+    let mut total = 0;
+    let mut rolls = Vec::new();
+    for term in self.terms.iter() {
+      match *term {
+        DiceTerm::Constant(n) => total = i32::saturating_add(total, n),
+        DiceTerm::Dice { count, sides, explode, keep, negative } => {
+          let first = rolls.len();
+          for _ in 0..count {
+            let mut value = rng.gen_range(1..=sides);
+            rolls.push(DieRoll {
+              sides, value, kept: true, negative, exploded: false,
+            });
+            let mut explosions = 0;
+            while explode && value == sides && explosions < MAX_EXPLOSIONS {
+              value = rng.gen_range(1..=sides);
+              rolls.push(DieRoll {
+                sides, value, kept: true, negative, exploded: true,
+              });
+              explosions += 1;
+            }
+          }
+
+          let pool = &mut rolls[first..];
+          let mut order: Vec<usize> = (0..pool.len()).collect();
+          order.sort_by_key(|i| pool[*i].value);
+          let dropped = match keep {
+            Keep::All => &order[..0],
+            Keep::Highest(n) => {
+              &order[..pool.len().saturating_sub(n as usize)]
+            }
+            Keep::Lowest(n) => &order[(n as usize).min(pool.len())..],
+          };
+          dropped.iter().for_each(|i| pool[*i].kept = false);
+
+          let sum: i32 = pool
+            .iter()
+            .filter(|die| die.kept)
+            .map(|die| die.value as i32)
+            .sum();
+          if negative {
+            total = i32::saturating_sub(total, sum);
+          } else {
+            total = i32::saturating_add(total, sum);
+          }
+        }
+      }
+    }
+    DiceRoll { total, rolls }
+  }
+}
+
+impl std::str::FromStr for DiceExpression {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s)
+  }
+}
+
+struct Parser<'a> {
+  source: &'a str,
+  chars: Vec<char>,
+  pos: usize,
+}
+
+impl Parser<'_> {
+  fn at_end(&self) -> bool {
+    self.pos >= self.chars.len()
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    if self.chars.get(self.pos) == Some(&c) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn error(&self, message: &str) -> anyhow::Error {
+    anyhow::Error::msg(format!(
+      "invalid dice expression \"{}\": {message}",
+      self.source
+    ))
+  }
+
+  fn number(&mut self) -> anyhow::Result<Option<u32>> {
+    let start = self.pos;
+    while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+      self.pos += 1;
+    }
+    if start == self.pos {
+      return Ok(None);
+    }
+    let digits: String = self.chars[start..self.pos].iter().collect();
+    digits
+      .parse()
+      .map(Some)
+      .map_err(|_| self.error("number is too large"))
+  }
+
+  fn term(&mut self, negative: bool) -> anyhow::Result<DiceTerm> {
+    let count = self.number()?;
+    if !self.eat('d') {
+      return match count {
+        Some(n) => Ok(DiceTerm::Constant(
+          i32::try_from(n).map_err(|_| self.error("number is too large"))?
+            * if negative { -1 } else { 1 },
+        )),
+        None => Err(self.error("expected a number or dice")),
+      };
+    }
+
+    let count = count.unwrap_or(1);
+    let sides = if self.eat('%') {
+      100
+    } else {
+      self.number()?.ok_or_else(|| self.error("expected die size"))?
+    };
+    if count == 0 || count > MAX_DICE {
+      return Err(self.error(&format!("dice count must be 1-{MAX_DICE}")));
+    }
+    if sides == 0 || sides > MAX_SIDES {
+      return Err(self.error(&format!("die size must be 1-{MAX_SIDES}")));
+    }
+
+    let explode = self.eat('!');
+    if explode && sides == 1 {
+      return Err(self.error("a one-sided die can't explode"));
+    }
+
+    let keep = if self.eat('k') {
+      let highest = if self.eat('l') {
+        false
+      } else {
+        self.eat('h');
+        true
+      };
+      let n = self
+        .number()?
+        .ok_or_else(|| self.error("expected number of dice to keep"))?;
+      if highest {
+        Keep::Highest(n)
+      } else {
+        Keep::Lowest(n)
+      }
+    } else {
+      Keep::All
+    };
+
+    Ok(DiceTerm::Dice { count, sides, explode, keep, negative })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rand::SeedableRng;
+
+  fn rng() -> rand::rngs::StdRng {
+    rand::rngs::StdRng::seed_from_u64(1)
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    for bad in ["", "d", "3d", "3x6", "2d6+", "0d6", "1d1!", "4d6k", "1d6*2"] {
+      assert!(DiceExpression::parse(bad).is_err(), "{bad} parsed");
+    }
+  }
+
+  #[test]
+  fn test_large_constants() {
+    let mut rng = rng();
+    let expr = DiceExpression::parse("2147483647+1").unwrap();
+    assert_eq!(expr.roll(&mut rng).total, i32::MAX);
+    let expr = DiceExpression::parse("-2147483647-1d6").unwrap();
+    assert_eq!(expr.roll(&mut rng).total, i32::MIN);
+  }
+
+  #[test]
+  fn test_bounds() {
+    let expr = DiceExpression::parse("3d6+2").unwrap();
+    let mut rng = rng();
+    for _ in 0..1000 {
+      let roll = expr.roll(&mut rng);
+      assert!((5..=20).contains(&roll.total));
+      assert_eq!(roll.rolls.len(), 3);
+    }
+  }
+
+  #[test]
+  fn test_keep_highest() {
+    let expr = DiceExpression::parse("4d6kh3").unwrap();
+    let mut rng = rng();
+    for _ in 0..1000 {
+      let roll = expr.roll(&mut rng);
+      let kept: Vec<_> = roll.rolls.iter().filter(|d| d.kept).collect();
+      assert_eq!(kept.len(), 3);
+      let dropped = roll.rolls.iter().find(|d| !d.kept).unwrap();
+      assert!(kept.iter().all(|d| d.value >= dropped.value));
+      let sum: i32 = kept.iter().map(|d| d.value as i32).sum();
+      assert_eq!(roll.total, sum);
+    }
+  }
+
+  #[test]
+  fn test_subtraction() {
+    let expr = DiceExpression::parse("2d10 - 1d4").unwrap();
+    let mut rng = rng();
+    for _ in 0..1000 {
+      let roll = expr.roll(&mut rng);
+      assert!((-2..=19).contains(&roll.total));
+      assert_eq!(roll.rolls.iter().filter(|d| d.negative).count(), 1);
+    }
+  }
+
+  #[test]
+  fn test_exploding() {
+    let expr = DiceExpression::parse("1d2!").unwrap();
+    let mut rng = rng();
+    let mut saw_explosion = false;
+    for _ in 0..1000 {
+      let roll = expr.roll(&mut rng);
+      let exploded = roll.rolls.iter().filter(|d| d.exploded).count();
+      saw_explosion |= exploded > 0;
+      // Every die except the last must have rolled a maximum
+      let last = roll.rolls.len() - 1;
+      assert!(roll.rolls[..last].iter().all(|d| d.value == 2));
+      assert_eq!(exploded, last);
+    }
+    assert!(saw_explosion);
+  }
+}
@@ -0,0 +1,138 @@
+// Non-uniform sampling shared by both `RandomNumberGenerator` variants.
+// Invalid parameters panic, just like an empty `range` does.
+use rand::Rng;
+use rand_distr::{
+  Binomial, Distribution, Exp, Normal, Poisson, Triangular,
+};
+
+pub(crate) fn chance<R: Rng + ?Sized>(rng: &mut R, probability: f64) -> bool {
+  // `clamp` passes NaN through, and `gen_bool` panics on it
+  !probability.is_nan() && rng.gen_bool(probability.clamp(0.0, 1.0))
+}
+
+pub(crate) fn normal<R: Rng + ?Sized>(
+  rng: &mut R,
+  mean: f32,
+  std_dev: f32,
+) -> f32 {
+  Normal::new(mean, std_dev)
+    .unwrap_or_else(|e| panic!("Invalid normal distribution: {e}"))
+    .sample(rng)
+}
+
+pub(crate) fn exponential<R: Rng + ?Sized>(rng: &mut R, rate: f32) -> f32 {
+  Exp::new(rate)
+    .unwrap_or_else(|e| panic!("Invalid exponential distribution: {e}"))
+    .sample(rng)
+}
+
+pub(crate) fn poisson<R: Rng + ?Sized>(rng: &mut R, mean: f64) -> u64 {
+  // `Poisson::new` accepts an infinite mean
+  assert!(
+    mean.is_finite() && mean > 0.0,
+    "Invalid Poisson distribution: mean must be positive and finite"
+  );
+  let sample: f64 = Poisson::new(mean)
+    .unwrap_or_else(|e| panic!("Invalid Poisson distribution: {e}"))
+    .sample(rng);
+  sample as u64
+}
+
+pub(crate) fn binomial<R: Rng + ?Sized>(
+  rng: &mut R,
+  trials: u64,
+  probability: f64,
+) -> u64 {
+  Binomial::new(trials, probability)
+    .unwrap_or_else(|e| panic!("Invalid binomial distribution: {e}"))
+    .sample(rng)
+}
+
+pub(crate) fn triangular<R: Rng + ?Sized>(
+  rng: &mut R,
+  min: f32,
+  max: f32,
+  mode: f32,
+) -> f32 {
+  Triangular::new(min, max, mode)
+    .unwrap_or_else(|e| panic!("Invalid triangular distribution: {e}"))
+    .sample(rng)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rand::SeedableRng;
+
+  const SAMPLES: usize = 10_000;
+
+  fn rng() -> rand::rngs::StdRng {
+    rand::rngs::StdRng::seed_from_u64(1)
+  }
+
+  fn mean(samples: impl Iterator<Item = f32>) -> f32 {
+    samples.sum::<f32>() / SAMPLES as f32
+  }
+
+  #[test]
+  fn test_chance() {
+    let mut rng = rng();
+    assert!((0..100).all(|_| !chance(&mut rng, 0.0)));
+    assert!((0..100).all(|_| chance(&mut rng, 1.0)));
+    assert!((0..100).all(|_| chance(&mut rng, 2.0)));
+    assert!((0..100).all(|_| !chance(&mut rng, f64::NAN)));
+    let hits = (0..SAMPLES).filter(|_| chance(&mut rng, 0.25)).count();
+    assert!((2200..2800).contains(&hits));
+  }
+
+  #[test]
+  fn test_normal() {
+    let mut rng = rng();
+    let samples: Vec<f32> =
+      (0..SAMPLES).map(|_| normal(&mut rng, 10.0, 2.0)).collect();
+    let m = mean(samples.iter().copied());
+    let variance = mean(samples.iter().map(|s| (s - m) * (s - m)));
+    assert!((m - 10.0).abs() < 0.1);
+    assert!((variance.sqrt() - 2.0).abs() < 0.1);
+  }
+
+  #[test]
+  fn test_exponential() {
+    let mut rng = rng();
+    let m = mean((0..SAMPLES).map(|_| exponential(&mut rng, 0.5)));
+    assert!((m - 2.0).abs() < 0.1);
+  }
+
+  #[test]
+  fn test_poisson_and_binomial() {
+    let mut rng = rng();
+    let m = mean((0..SAMPLES).map(|_| poisson(&mut rng, 3.0) as f32));
+    assert!((m - 3.0).abs() < 0.1);
+    let m = mean((0..SAMPLES).map(|_| binomial(&mut rng, 10, 0.3) as f32));
+    assert!((m - 3.0).abs() < 0.1);
+    assert!((0..SAMPLES).all(|_| binomial(&mut rng, 10, 0.3) <= 10));
+  }
+
+  #[test]
+  fn test_triangular() {
+    let mut rng = rng();
+    let samples: Vec<f32> =
+      (0..SAMPLES).map(|_| triangular(&mut rng, 0.0, 9.0, 3.0)).collect();
+    assert!(samples.iter().all(|s| (0.0..=9.0).contains(s)));
+    assert!((mean(samples.into_iter()) - 4.0).abs() < 0.1);
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_invalid_parameters() {
+    let mut rng = rng();
+    normal(&mut rng, 0.0, f32::NAN);
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_infinite_poisson() {
+    let mut rng = rng();
+    poisson(&mut rng, f64::INFINITY);
+  }
+}
@@ -0,0 +1,286 @@
+// Random points in shapes, shared by both `RandomNumberGenerator`
+// variants, and Poisson-disk sampling.
+use crate::{RandomNumberGenerator, Rect2D};
+use bevy::{platform::collections::HashMap, prelude::*};
+use rand::Rng;
+use std::f32::consts::TAU;
+
+pub(crate) fn point_in_circle<R: Rng + ?Sized>(
+  rng: &mut R,
+  center: Vec2,
+  radius: f32,
+) -> Vec2 {
+  point_on_ring(rng, center, 0.0, radius)
+}
+
+pub(crate) fn point_on_ring<R: Rng + ?Sized>(
+  rng: &mut R,
+  center: Vec2,
+  inner_radius: f32,
+  outer_radius: f32,
+) -> Vec2 {
+  // Picking the squared radius uniformly spreads points evenly over the
+  // ring's area, rather than bunching them up near the center.
+  let inner = inner_radius * inner_radius;
+  let outer = outer_radius * outer_radius;
+  let radius = if inner == outer {
+    outer_radius
+  } else {
+    rng.gen_range(inner.min(outer)..=inner.max(outer)).sqrt()
+  };
+  center + Vec2::from_angle(rng.gen_range(0.0..TAU)) * radius
+}
+
+pub(crate) fn point_in_rect<R: Rng + ?Sized>(
+  rng: &mut R,
+  rect: &Rect2D,
+) -> Vec2 {
+  Vec2::new(
+    rng.gen_range(rect.min().x..=rect.max().x),
+    rng.gen_range(rect.min().y..=rect.max().y),
+  )
+}
+
+pub(crate) fn point_on_segment<R: Rng + ?Sized>(
+  rng: &mut R,
+  start: Vec2,
+  end: Vec2,
+) -> Vec2 {
+  start.lerp(end, rng.gen_range(0.0..=1.0))
+}
+
+/// `PoissonDisk` generates "blue noise": random points that are never
+/// closer than a minimum distance to each other. Use it to scatter
+/// items, enemies or decoration without clumps or overlaps.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::{PoissonDisk, RandomNumberGenerator, Rect2D};
+/// use bevy::prelude::Vec2;
+/// let mut rng = RandomNumberGenerator::seeded(1);
+/// let bounds = Rect2D::new(Vec2::ZERO, Vec2::new(100.0, 100.0));
+/// let trees = PoissonDisk::new(10.0).sample(&mut rng, &bounds);
+/// assert!(trees.iter().all(|a| trees.iter()
+///   .all(|b| a == b || a.distance(*b) >= 10.0)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PoissonDisk {
+  min_distance: f32,
+  attempts: u32,
+}
+
+impl PoissonDisk {
+  /// Creates a sampler whose points are at least `min_distance` apart.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `min_distance` isn't positive.
+  pub fn new(min_distance: f32) -> Self {
+    assert!(min_distance > 0.0, "Poisson-disk spacing must be positive");
+    Self {
+      min_distance,
+      attempts: 30,
+    }
+  }
+
+  /// Sets how many candidates are tried around each point before giving
+  /// up on it (30 by default). More attempts pack points more tightly,
+  /// at the cost of speed.
+  pub fn with_attempts(mut self, attempts: u32) -> Self {
+    self.attempts = attempts.max(1);
+    self
+  }
+
+  /// Fills `bounds` with points, using Bridson's algorithm.
+  pub fn sample(
+    &self,
+    rng: &mut RandomNumberGenerator,
+    bounds: &Rect2D,
+  ) -> Vec<Vec2> {
+    self.sample_where(rng, bounds, |_| true)
+  }
+
+  /// Fills the parts of `bounds` for which `accept` returns `true`. The
+  /// points spread out from the first accepted point, so regions that
+  /// aren't connected to it by accepted space may be left empty; use
+  /// [`PoissonDisk::select`] to pick from known valid positions instead.
+  pub fn sample_where(
+    &self,
+    rng: &mut RandomNumberGenerator,
+    bounds: &Rect2D,
+    accept: impl Fn(Vec2) -> bool,
+  ) -> Vec<Vec2> {
+    let mut grid = Grid::new(self.min_distance);
+    let mut active = Vec::new();
+    let first = (0..self.attempts)
+      .map(|_| point_in_rect(rng, bounds))
+      .find(|point| accept(*point));
+    if let Some(point) = first {
+      grid.insert(point);
+      active.push(point);
+    }
+
+    while !active.is_empty() {
+      let index = rng.gen_range(0..active.len());
+      let origin = active[index];
+      let found = (0..self.attempts)
+        .map(|_| {
+          let (near, far) = (self.min_distance, self.min_distance * 2.0);
+          point_on_ring(rng, origin, near, far)
+        })
+        .find(|point| {
+          bounds.contains(*point) && accept(*point) && grid.is_clear(*point)
+        });
+      match found {
+        Some(point) => {
+          grid.insert(point);
+          active.push(point);
+        }
+        None => {
+          active.swap_remove(index);
+        }
+      }
+    }
+    grid.points
+  }
+
+  /// Picks as many of `candidates` as possible, in random order, such
+  /// that no two picked positions are too close together. This is the
+  /// discrete version of [`PoissonDisk::sample_where`], for when the
+  /// valid positions (open floor tiles, for example) are already known.
+  pub fn select(
+    &self,
+    rng: &mut RandomNumberGenerator,
+    candidates: &[Vec2],
+  ) -> Vec<Vec2> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    rng.shuffle(&mut order);
+    let mut grid = Grid::new(self.min_distance);
+    for point in order.into_iter().map(|index| candidates[index]) {
+      if grid.is_clear(point) {
+        grid.insert(point);
+      }
+    }
+    grid.points
+  }
+}
+
+// A spatial hash with cells as wide as the minimum distance, so only the
+// surrounding 3x3 cells need checking for neighbors.
+struct Grid {
+  min_distance: f32,
+  cells: HashMap<IVec2, Vec<usize>>,
+  points: Vec<Vec2>,
+}
+
+impl Grid {
+  fn new(min_distance: f32) -> Self {
+    Self {
+      min_distance,
+      cells: HashMap::new(),
+      points: Vec::new(),
+    }
+  }
+
+  fn cell(&self, point: Vec2) -> IVec2 {
+    (point / self.min_distance).floor().as_ivec2()
+  }
+
+  fn is_clear(&self, point: Vec2) -> bool {
+    let cell = self.cell(point);
+    (-1..=1).all(|y| {
+      (-1..=1).all(|x| {
+        self.cells.get(&(cell + IVec2::new(x, y))).is_none_or(|indices| {
+          indices.iter().all(|i| {
+            self.points[*i].distance_squared(point)
+              >= self.min_distance * self.min_distance
+          })
+        })
+      })
+    })
+  }
+
+  fn insert(&mut self, point: Vec2) {
+    let cell = self.cell(point);
+    self.cells.entry(cell).or_default().push(self.points.len());
+    self.points.push(point);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rand::SeedableRng;
+
+  fn rng() -> rand::rngs::StdRng {
+    rand::rngs::StdRng::seed_from_u64(1)
+  }
+
+  fn assert_spaced(points: &[Vec2], min_distance: f32) {
+    for (i, a) in points.iter().enumerate() {
+      for b in points[i + 1..].iter() {
+        assert!(a.distance(*b) >= min_distance, "{a} and {b} are too close");
+      }
+    }
+  }
+
+  #[test]
+  fn test_shapes() {
+    let mut rng = rng();
+    let center = Vec2::new(10.0, -5.0);
+    let rect = Rect2D::new(Vec2::new(-1.0, 2.0), Vec2::new(3.0, 4.0));
+    for _ in 0..1000 {
+      let point = point_in_circle(&mut rng, center, 2.0);
+      assert!(point.distance(center) <= 2.0 + 1e-4);
+      let point = point_on_ring(&mut rng, center, 3.0, 4.0);
+      assert!((3.0 - 1e-4..=4.0 + 1e-4).contains(&point.distance(center)));
+      let point = point_on_ring(&mut rng, center, 5.0, 5.0);
+      assert!((point.distance(center) - 5.0).abs() < 1e-4);
+      assert!(rect.contains(point_in_rect(&mut rng, &rect)));
+      let point = point_on_segment(&mut rng, Vec2::ZERO, Vec2::new(2.0, 4.0));
+      assert!((point.y - point.x * 2.0).abs() < 1e-4);
+      assert!((0.0..=2.0).contains(&point.x));
+    }
+  }
+
+  #[test]
+  fn test_circle_is_uniform() {
+    // Half of a circle's area is within 1/sqrt(2) of its center
+    let mut rng = rng();
+    let inner = (0..10_000)
+      .map(|_| point_in_circle(&mut rng, Vec2::ZERO, 1.0))
+      .filter(|point| point.length() < 0.5_f32.sqrt())
+      .count();
+    assert!((4800..5200).contains(&inner));
+  }
+
+  #[test]
+  fn test_poisson_disk() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let bounds = Rect2D::new(Vec2::ZERO, Vec2::new(100.0, 100.0));
+    let points = PoissonDisk::new(5.0).sample(&mut rng, &bounds);
+    assert_spaced(&points, 5.0);
+    assert!(points.iter().all(|point| bounds.contains(*point)));
+    // A well-packed sample covers the area fairly densely
+    assert!(points.len() > 200);
+
+    let left = PoissonDisk::new(5.0)
+      .sample_where(&mut rng, &bounds, |point| point.x < 50.0);
+    assert_spaced(&left, 5.0);
+    assert!(left.iter().all(|point| point.x < 50.0));
+  }
+
+  #[test]
+  fn test_select() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let candidates: Vec<Vec2> = (0..50)
+      .flat_map(|y| (0..50).map(move |x| Vec2::new(x as f32, y as f32)))
+      .collect();
+    let picked = PoissonDisk::new(4.0).select(&mut rng, &candidates);
+    assert_spaced(&picked, 4.0);
+    assert!(picked.iter().all(|point| candidates.contains(point)));
+    assert!(picked.len() > 50);
+    assert!(PoissonDisk::new(4.0).select(&mut rng, &[]).is_empty());
+  }
+}
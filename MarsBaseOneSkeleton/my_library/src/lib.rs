@@ -6,23 +6,64 @@
 //! `my_library` includes:<callout id="docs.keyword" />
 //! 
 //! * Random number generation facilities.<callout id="docs.bullet" />
+//! * Non-uniform distributions (normal, exponential, Poisson, binomial,
+//!   triangular) and simple `chance` rolls.
+//! * Shuffling, choosing and sampling from slices and iterators.
+//! * Dice-notation rolling (`3d6+2`, `4d6kh3`, `1d6!`).
+//! * Weighted random tables for loot and spawn selection.
+//! * Saving and restoring random number generator state.
+//! * Named, independent random number streams derived from one seed.
+//! * A Bevy plugin that reports its seed, and can be given one (in code,
+//!   with `--seed` or with `RNG_SEED`) to reproduce a run.
+//! * Coherent noise (Perlin, OpenSimplex2, Worley, fBm and domain
+//!   warping).
+//! * Random points in circles, rings, rectangles and segments, and
+//!   Poisson-disk (evenly spaced) point sets.
+//! * Procedural, pronounceable names from a Markov chain.
+//! * Statistical quality tests for the random number generators.
 //! 
 //! ## Feature Flags
 //! 
-//! The following feature flags are supported: `xorshift`, `pcg`, `locking`.
+//! The following feature flags are supported: `xorshift`, `pcg`, `locking`,
+//! `hot_reload`, `embedded_assets`.
 //! 
 //! ### Random Number Generation
 //! 
 //! * The `locking` feature enables interior mutability inside 
-//! [`RandomNumberGenerator`], <callout id="docs.code_link" />
+//!   [`RandomNumberGenerator`], <callout id="docs.code_link" />
 //!   allowing it to be used as a resource (`Res<RandomNumberGenerator`) 
-//! rather than requiring mutability (`ResMut<RandomNumberGenerator>`)
-//! * You can control which random number generation algorithm is used by 
-//! specifying *one* of:
-//!    * `xorshift` to use the XorShift algorithm.
-//!    * `pcg` to use the PCG algorithm.
+//!   rather than requiring mutability (`ResMut<RandomNumberGenerator>`).
+//!   Each thread draws from its own generator derived from the seed, so
+//!   threads don't wait for each other. Only numbers drawn on the thread
+//!   that created the generator are reproducible from the seed.
+//! * `pcg` and `xorshift` add the PCG and XorShift algorithms to the
+//!   always-available `StdRng`. They can be enabled together, and an
+//!   [`Algorithm`] picked at runtime with
+//!   `RandomNumberGenerator::with_algorithm`. PCG is the default when
+//!   it is enabled.
+//!
+//! ### Assets
+//!
+//! * The `hot_reload` feature is meant for development. It watches the
+//!   `assets` directory: edited images and sounds are reloaded while
+//!   the game runs, sprite sheets whose image is resized gain or lose
+//!   sprites, and changes to asset manifests (see
+//!   `AssetManager::add_manifest`) are applied.
+//! * The `embedded_assets` feature compiles the game's assets into its
+//!   binary (see `embed_assets!`), so it runs from any directory. Every
+//!   file the game's build script lists is embedded, even if it isn't
+//!   added to the `AssetManager`.
+//! * Asset groups (`AssetManager::add_group`) are only loaded while the
+//!   game is in the states that use them, set with
+//!   `GameStatePlugin::with_asset_group`.
+//! * Sounds play through an `AudioMixer`, with music, sound effect and
+//!   UI buses. `GameStatePlugin::with_music` crossfades background
+//!   music between states.
 //END: head_doc
 
+mod backend;
+pub use backend::{Algorithm, RngState};
+
 #[cfg(not(feature = "locking"))]
 mod random;
 #[cfg(not(feature = "locking"))]
@@ -33,6 +74,25 @@ mod random_locking;
 #[cfg(feature = "locking")]
 pub use random_locking::*;
 
+mod random_streams;
+pub use random_streams::*;
+mod random_plugin;
+pub use random_plugin::*;
+
+mod distributions;
+mod dice;
+pub use dice::*;
+mod weighted_table;
+pub use weighted_table::*;
+mod noise;
+pub use noise::*;
+mod geometry;
+pub use geometry::PoissonDisk;
+mod names;
+pub use names::*;
+mod quality;
+pub use quality::*;
+
 /// [`RandomNumberGenerator`] wraps the `rand` crate. The `rand` crate
 /// is re-exported for your convenience.
 pub use rand;
@@ -0,0 +1,253 @@
+use crate::RandomNumberGenerator;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Marks the start and end of a word inside the chain. Neither can
+// appear in (lower-cased, alphabetic) training words.
+const START: char = '^';
+const END: char = '$';
+
+// Give up on a name after this many rejected attempts.
+const MAX_ATTEMPTS: usize = 1_000;
+
+/// `NameGenerator` invents pronounceable names. It learns which letters
+/// follow each short run of letters in a list of example words (a
+/// character-level Markov chain), and strings letters together the same
+/// way. Train it on names from the setting you want: Roman names give
+/// Roman-sounding results, and so on.
+///
+/// Generation only uses the [`RandomNumberGenerator`] it is given, so a
+/// seeded generator always produces the same names.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::{NameGenerator, RandomNumberGenerator};
+/// let names = NameGenerator::new(2)
+///   .train(["ares", "hermes", "helios", "selene", "nyx", "eos"])
+///   .with_length(3, 8);
+/// let mut rng = RandomNumberGenerator::seeded(1);
+/// let name = names.generate(&mut rng).unwrap();
+/// assert!((3..=8).contains(&name.chars().count()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NameGenerator {
+  order: usize,
+  // For each run of `order` letters, how often each letter follows it.
+  // `BTreeMap`s keep iteration order (and so the output for a seed)
+  // stable between runs.
+  transitions: BTreeMap<String, BTreeMap<char, u32>>,
+  words: BTreeSet<String>,
+  min_length: usize,
+  max_length: usize,
+  allow_training_words: bool,
+}
+
+impl NameGenerator {
+  /// Creates an untrained generator that looks at the previous `order`
+  /// letters to pick the next. 2 or 3 works well: lower orders give
+  /// wilder names, higher orders stick closer to the training words.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `order` is zero.
+  pub fn new(order: usize) -> Self {
+    assert!(order > 0, "A name generator's order must be at least 1");
+    Self {
+      order,
+      transitions: BTreeMap::new(),
+      words: BTreeSet::new(),
+      min_length: 3,
+      max_length: 12,
+      allow_training_words: false,
+    }
+  }
+
+  /// Learns from a list of example words. Words are lower-cased, and
+  /// anything other than letters, spaces, hyphens and apostrophes is
+  /// dropped. Can be called repeatedly to add more words.
+  pub fn train<S: AsRef<str>>(
+    mut self,
+    words: impl IntoIterator<Item = S>,
+  ) -> Self {
+    for word in words {
+      let word: String = word
+        .as_ref()
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphabetic() || matches!(c, ' ' | '-' | '\''))
+        .collect();
+      if word.is_empty() {
+        continue;
+      }
+      let chars: Vec<char> = std::iter::repeat_n(START, self.order)
+        .chain(word.chars())
+        .chain(std::iter::once(END))
+        .collect();
+      for window in chars.windows(self.order + 1) {
+        let context: String = window[..self.order].iter().collect();
+        *self
+          .transitions
+          .entry(context)
+          .or_default()
+          .entry(window[self.order])
+          .or_default() += 1;
+      }
+      self.words.insert(word);
+    }
+    self
+  }
+
+  /// Sets the shortest and longest names (in letters) to generate. The
+  /// defaults are 3 and 12.
+  pub fn with_length(mut self, min: usize, max: usize) -> Self {
+    self.min_length = min.max(1);
+    self.max_length = max.max(self.min_length);
+    self
+  }
+
+  /// By default, names that appear in the training words are rejected so
+  /// that every name is new. Pass `true` to allow them.
+  pub fn allow_training_words(mut self, allow: bool) -> Self {
+    self.allow_training_words = allow;
+    self
+  }
+
+  /// Generates a capitalized name. Returns `None` if the generator hasn't
+  /// been trained, or if the training words can't produce a name that
+  /// meets the length (and novelty) requirements.
+  pub fn generate(&self, rng: &mut RandomNumberGenerator) -> Option<String> {
+    (0..MAX_ATTEMPTS)
+      .find_map(|_| self.attempt(rng))
+      .map(|name| capitalize(&name))
+  }
+
+  /// Generates `count` different names, stopping early if it runs out of
+  /// new ones.
+  pub fn generate_many(
+    &self,
+    rng: &mut RandomNumberGenerator,
+    count: usize,
+  ) -> Vec<String> {
+    let mut names = Vec::with_capacity(count);
+    let mut misses = 0;
+    while names.len() < count && misses < MAX_ATTEMPTS {
+      match self.generate(rng) {
+        Some(name) if !names.contains(&name) => names.push(name),
+        Some(_) => misses += 1,
+        None => break,
+      }
+    }
+    names
+  }
+
+  fn attempt(&self, rng: &mut RandomNumberGenerator) -> Option<String> {
+    let mut context: Vec<char> = vec![START; self.order];
+    let mut name = String::new();
+    let mut length = 0;
+    loop {
+      let key: String = context.iter().collect();
+      let next = pick(self.transitions.get(&key)?, rng);
+      if next == END {
+        break;
+      }
+      length += 1;
+      if length > self.max_length {
+        return None;
+      }
+      name.push(next);
+      context.remove(0);
+      context.push(next);
+    }
+    let valid = length >= self.min_length
+      && (self.allow_training_words || !self.words.contains(&name));
+    valid.then_some(name)
+  }
+}
+
+fn pick(counts: &BTreeMap<char, u32>, rng: &mut RandomNumberGenerator) -> char {
+  let total: u32 = counts.values().sum();
+  let mut roll = rng.range(0..total);
+  for (c, count) in counts.iter() {
+    if roll < *count {
+      return *c;
+    }
+    roll -= count;
+  }
+  END
+}
+
+// Capitalizes the first letter of each word ("van der berg" becomes
+// "Van Der Berg").
+fn capitalize(name: &str) -> String {
+  let mut result = String::with_capacity(name.len());
+  let mut start_of_word = true;
+  for c in name.chars() {
+    if start_of_word {
+      result.extend(c.to_uppercase());
+    } else {
+      result.push(c);
+    }
+    start_of_word = matches!(c, ' ' | '-');
+  }
+  result
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const WORDS: [&str; 12] = [
+    "amelia", "amos", "bruno", "clara", "dmitri", "elena", "felix",
+    "greta", "hugo", "ingrid", "marta", "oskar",
+  ];
+
+  #[test]
+  fn test_untrained() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    assert!(NameGenerator::new(2).generate(&mut rng).is_none());
+  }
+
+  #[test]
+  fn test_lengths_and_novelty() {
+    let names = NameGenerator::new(2).train(WORDS).with_length(4, 7);
+    let mut rng = RandomNumberGenerator::seeded(1);
+    for _ in 0..100 {
+      let name = names.generate(&mut rng).unwrap();
+      assert!((4..=7).contains(&name.chars().count()), "{name}");
+      assert!(name.chars().next().unwrap().is_uppercase());
+      assert!(!WORDS.contains(&name.to_lowercase().as_str()), "{name}");
+    }
+  }
+
+  #[test]
+  fn test_reproducibility() {
+    let names = NameGenerator::new(2).train(WORDS);
+    let mut rng = (
+      RandomNumberGenerator::seeded(1),
+      RandomNumberGenerator::seeded(1),
+    );
+    assert_eq!(
+      names.generate_many(&mut rng.0, 10),
+      names.generate_many(&mut rng.1, 10)
+    );
+  }
+
+  #[test]
+  fn test_generate_many() {
+    let names = NameGenerator::new(3).train(["bob", "bobby"]);
+    let mut rng = RandomNumberGenerator::seeded(1);
+    // Order 3 can only reproduce the training words
+    assert!(names.generate_many(&mut rng, 5).is_empty());
+    let names = names.allow_training_words(true);
+    let mut generated = names.generate_many(&mut rng, 5);
+    generated.sort();
+    assert_eq!(generated, vec!["Bob".to_string(), "Bobby".to_string()]);
+  }
+
+  #[test]
+  fn test_capitalize() {
+    assert_eq!(capitalize("van der berg"), "Van Der Berg");
+    assert_eq!(capitalize("jean-luc"), "Jean-Luc");
+  }
+}
@@ -0,0 +1,521 @@
+use crate::RandomNumberGenerator;
+
+/// The kinds of coherent noise [`Noise`] can generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseType {
+  /// Ken Perlin's "improved" gradient noise. Output is roughly -1..1.
+  Perlin,
+  /// OpenSimplex2 noise (the "fast" variant, not the smoother 2S). It
+  /// has fewer axis-aligned artifacts than Perlin noise. In 2D it samples
+  /// the triangular simplex lattice with 24 evenly spread gradients; in
+  /// 3D it samples two offset body-centred cubic lattices rather than
+  /// Ken Perlin's simplex grid. Output is roughly -1..1.
+  OpenSimplex,
+  /// Cellular (Worley) noise: the distance to the nearest of a set of
+  /// randomly scattered points. Output is 0..1, and looks like cells,
+  /// stones or cracked ground.
+  Worley,
+}
+
+/// Settings for fractal Brownian motion (fBm): several octaves of
+/// noise, each at a higher frequency and lower amplitude, added
+/// together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fractal {
+  /// How many layers of noise to combine.
+  pub octaves: u32,
+  /// The frequency of the first octave. Smaller values give larger
+  /// features.
+  pub frequency: f32,
+  /// How much the frequency is multiplied by for each octave.
+  pub lacunarity: f32,
+  /// How much the amplitude is multiplied by for each octave.
+  pub gain: f32,
+}
+
+impl Default for Fractal {
+  fn default() -> Self {
+    Self {
+      octaves: 4,
+      frequency: 1.0,
+      lacunarity: 2.0,
+      gain: 0.5,
+    }
+  }
+}
+
+/// `Noise` generates coherent noise: random values that change smoothly
+/// as you move through space. It's useful for terrain, caves, clouds and
+/// anything else that should look organic rather than uniformly random.
+///
+/// `Noise` is seeded from a [`RandomNumberGenerator`], so the same seed
+/// always produces the same noise.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::*;
+/// let mut rng = RandomNumberGenerator::seeded(1);
+/// let noise = Noise::new(&mut rng);
+/// let fractal = Fractal { frequency: 0.05, ..Default::default() };
+/// let height = noise.fbm2(NoiseType::Perlin, 10.0, 20.0, &fractal);
+/// assert!(height >= -1.0 && height <= 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Noise {
+  // A shuffled permutation of 0..256, repeated twice so that lookups
+  // can add two indices without wrapping.
+  perm: Vec<u8>,
+}
+
+impl Noise {
+  /// Creates a noise generator, using `rng` to shuffle its permutation
+  /// table.
+  pub fn new(rng: &mut RandomNumberGenerator) -> Self {
+    let mut table: Vec<u8> = (0..=255).collect();
+    for i in (1..table.len()).rev() {
+      table.swap(i, rng.range(0..=i));
+    }
+    let perm = table.iter().chain(table.iter()).copied().collect();
+    Self { perm }
+  }
+
+  /// Creates a noise generator from a seed.
+  pub fn seeded(seed: u64) -> Self {
+    Self::new(&mut RandomNumberGenerator::seeded(seed))
+  }
+
+  /// Samples 2D noise of the requested type.
+  pub fn sample2(&self, noise_type: NoiseType, x: f32, y: f32) -> f32 {
+    match noise_type {
+      NoiseType::Perlin => self.perlin2(x, y),
+      NoiseType::OpenSimplex => self.open_simplex2(x, y),
+      NoiseType::Worley => self.worley2(x, y),
+    }
+  }
+
+  /// Samples 3D noise of the requested type.
+  pub fn sample3(
+    &self,
+    noise_type: NoiseType,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> f32 {
+    match noise_type {
+      NoiseType::Perlin => self.perlin3(x, y, z),
+      NoiseType::OpenSimplex => self.open_simplex3(x, y, z),
+      NoiseType::Worley => self.worley3(x, y, z),
+    }
+  }
+
+  /// 2D Perlin noise, roughly in the range -1..1.
+  pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+    let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+    let (xf, yf) = (x - x.floor(), y - y.floor());
+    let (u, v) = (fade(xf), fade(yf));
+    let corner = |cx: i32, cy: i32| {
+      let (dx, dy) = (xf - cx as f32, yf - cy as f32);
+      let hash = self.hash(&[xi.wrapping_add(cx), yi.wrapping_add(cy)]);
+      let (gx, gy) = GRADIENTS_2D[hash & 7];
+      gx * dx + gy * dy
+    };
+    let value = lerp(
+      v,
+      lerp(u, corner(0, 0), corner(1, 0)),
+      lerp(u, corner(0, 1), corner(1, 1)),
+    );
+    value.clamp(-1.0, 1.0)
+  }
+
+  /// 3D Perlin noise, roughly in the range -1..1.
+  pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+    let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+    let (xf, yf, zf) = (x - x.floor(), y - y.floor(), z - z.floor());
+    let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+    let corner = |cx: i32, cy: i32, cz: i32| {
+      let d = (xf - cx as f32, yf - cy as f32, zf - cz as f32);
+      let hash = self.hash(&[
+        xi.wrapping_add(cx),
+        yi.wrapping_add(cy),
+        zi.wrapping_add(cz),
+      ]);
+      let (gx, gy, gz) = GRADIENTS_3D[hash % 12];
+      gx * d.0 + gy * d.1 + gz * d.2
+    };
+    let value = lerp(
+      w,
+      lerp(
+        v,
+        lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+        lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+      ),
+      lerp(
+        v,
+        lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+        lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+      ),
+    );
+    value.clamp(-1.0, 1.0)
+  }
+
+  /// 2D OpenSimplex2 noise, roughly in the range -1..1.
+  pub fn open_simplex2(&self, x: f32, y: f32) -> f32 {
+    const SKEW: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+    const UNSKEW: f32 = -0.211_324_87; // (1 / sqrt(3) - 1) / 2
+    const NORMALIZE: f32 = 0.010_016_341;
+
+    // Skew into lattice space to find the containing triangle
+    let s = (x + y) * SKEW;
+    let (xs, ys) = ((x + s).floor(), (y + s).floor());
+    let (xi, yi) = (x + s - xs, y + s - ys);
+    let t = (xi + yi) * UNSKEW;
+    let (dx0, dy0) = (xi + t, yi + t);
+    let (i1, j1) = if dx0 > dy0 { (1, 0) } else { (0, 1) };
+    let (i, j) = (xs as i32, ys as i32);
+
+    let corners = [
+      (0, 0, dx0, dy0),
+      (i1, j1, dx0 - i1 as f32 - UNSKEW, dy0 - j1 as f32 - UNSKEW),
+      (1, 1, dx0 - 1.0 - 2.0 * UNSKEW, dy0 - 1.0 - 2.0 * UNSKEW),
+    ];
+    let total: f32 = corners
+      .iter()
+      .map(|&(ci, cj, dx, dy)| {
+        let falloff = 0.5 - dx * dx - dy * dy;
+        if falloff <= 0.0 {
+          return 0.0;
+        }
+        let hash = self.hash(&[i.wrapping_add(ci), j.wrapping_add(cj)]);
+        let (gx, gy) = open_simplex_gradient2(hash);
+        falloff.powi(4) * (gx * dx + gy * dy)
+      })
+      .sum();
+    (total / NORMALIZE).clamp(-1.0, 1.0)
+  }
+
+  /// 3D OpenSimplex2 noise, roughly in the range -1..1.
+  pub fn open_simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+    const NORMALIZE: f32 = 0.079_698_38;
+
+    // Rotate so that the lattice's main diagonal points along z, which
+    // hides its orientation
+    let r = (x + y + z) * (2.0 / 3.0);
+    let d = [r - x, r - y, r - z];
+    let base = d.map(|d| d.round());
+    let mut d = [d[0] - base[0], d[1] - base[1], d[2] - base[2]];
+    let mut base = base.map(|b| b as i32);
+
+    // Each of the two lattices contributes its closest point, and the
+    // next closest along the axis with the largest offset
+    let mut total = 0.0;
+    for lattice in 0..2 {
+      let sign = d.map(|d| if d > 0.0 { -1 } else { 1 });
+      let falloff = 0.6 - d.iter().map(|d| d * d).sum::<f32>();
+      let mut contribute = |falloff: f32, point: [i32; 3], d: [f32; 3]| {
+        if falloff <= 0.0 {
+          return;
+        }
+        let hash = self.hash(&[lattice, point[0], point[1], point[2]]);
+        let (gx, gy, gz) = open_simplex_gradient3(hash);
+        total += falloff.powi(4) * (gx * d[0] + gy * d[1] + gz * d[2]);
+      };
+      contribute(falloff, base, d);
+
+      let axis = (0..3)
+        .max_by(|&a, &b| d[a].abs().total_cmp(&d[b].abs()))
+        .unwrap_or(0);
+      let mut point = base;
+      point[axis] = point[axis].wrapping_sub(sign[axis]);
+      let mut next = d;
+      next[axis] += sign[axis] as f32;
+      contribute(falloff + 2.0 * d[axis].abs() - 1.0, point, next);
+
+      // Move to the second lattice, which is offset by half a cell
+      for axis in 0..3 {
+        d[axis] = sign[axis] as f32 * (0.5 - d[axis].abs());
+        if sign[axis] < 0 {
+          base[axis] = base[axis].wrapping_add(1);
+        }
+      }
+    }
+    (total / NORMALIZE).clamp(-1.0, 1.0)
+  }
+
+  /// 2D cellular (Worley) noise: the distance to the nearest feature
+  /// point, in the range 0..1.
+  pub fn worley2(&self, x: f32, y: f32) -> f32 {
+    let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+    let mut nearest = f32::MAX;
+    for cx in xi.saturating_sub(1)..=xi.saturating_add(1) {
+      for cy in yi.saturating_sub(1)..=yi.saturating_add(1) {
+        let px = cx as f32 + self.jitter(&[cx, cy, 0]);
+        let py = cy as f32 + self.jitter(&[cx, cy, 1]);
+        let (dx, dy) = (px - x, py - y);
+        nearest = nearest.min(dx * dx + dy * dy);
+      }
+    }
+    nearest.sqrt().min(1.0)
+  }
+
+  /// 3D cellular (Worley) noise: the distance to the nearest feature
+  /// point, in the range 0..1.
+  pub fn worley3(&self, x: f32, y: f32, z: f32) -> f32 {
+    let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+    let mut nearest = f32::MAX;
+    for cx in xi.saturating_sub(1)..=xi.saturating_add(1) {
+      for cy in yi.saturating_sub(1)..=yi.saturating_add(1) {
+        for cz in zi.saturating_sub(1)..=zi.saturating_add(1) {
+          let px = cx as f32 + self.jitter(&[cx, cy, cz, 0]);
+          let py = cy as f32 + self.jitter(&[cx, cy, cz, 1]);
+          let pz = cz as f32 + self.jitter(&[cx, cy, cz, 2]);
+          let (dx, dy, dz) = (px - x, py - y, pz - z);
+          nearest = nearest.min(dx * dx + dy * dy + dz * dz);
+        }
+      }
+    }
+    nearest.sqrt().min(1.0)
+  }
+
+  /// 2D fractal Brownian motion: `fractal.octaves` layers of noise added
+  /// together. The result stays within the range of the underlying
+  /// noise type.
+  pub fn fbm2(
+    &self,
+    noise_type: NoiseType,
+    x: f32,
+    y: f32,
+    fractal: &Fractal,
+  ) -> f32 {
+    fbm(fractal, |frequency| {
+      self.sample2(noise_type, x * frequency, y * frequency)
+    })
+  }
+
+  /// 3D fractal Brownian motion.
+  pub fn fbm3(
+    &self,
+    noise_type: NoiseType,
+    x: f32,
+    y: f32,
+    z: f32,
+    fractal: &Fractal,
+  ) -> f32 {
+    fbm(fractal, |frequency| {
+      self.sample3(noise_type, x * frequency, y * frequency, z * frequency)
+    })
+  }
+
+  /// 2D domain-warped fBm. The sample position is pushed around by
+  /// another layer of fBm before sampling, which turns regular blobs
+  /// into swirling, organic shapes.
+  ///
+  /// # Arguments
+  ///
+  /// * `strength` - how far (in the same units as `x` and `y`) the
+  ///   sample position can be displaced.
+  pub fn warp2(
+    &self,
+    noise_type: NoiseType,
+    x: f32,
+    y: f32,
+    fractal: &Fractal,
+    strength: f32,
+  ) -> f32 {
+    // Offsets decorrelate the two displacement axes
+    let qx = self.fbm2(noise_type, x, y, fractal);
+    let qy = self.fbm2(noise_type, x + 52.7, y + 13.1, fractal);
+    self.fbm2(noise_type, x + strength * qx, y + strength * qy, fractal)
+  }
+
+  /// 3D domain-warped fBm.
+  pub fn warp3(
+    &self,
+    noise_type: NoiseType,
+    x: f32,
+    y: f32,
+    z: f32,
+    fractal: &Fractal,
+    strength: f32,
+  ) -> f32 {
+    let qx = self.fbm3(noise_type, x, y, z, fractal);
+    let qy = self.fbm3(noise_type, x + 52.7, y + 13.1, z + 7.9, fractal);
+    let qz = self.fbm3(noise_type, x + 31.3, y + 71.9, z + 23.5, fractal);
+    self.fbm3(
+      noise_type,
+      x + strength * qx,
+      y + strength * qy,
+      z + strength * qz,
+      fractal,
+    )
+  }
+
+  fn hash(&self, coords: &[i32]) -> usize {
+    coords.iter().fold(0, |hash, coord| {
+      self.perm[hash + (coord & 255) as usize] as usize
+    })
+  }
+
+  // A repeatable pseudo-random offset (0..1) for a lattice cell.
+  fn jitter(&self, coords: &[i32]) -> f32 {
+    self.hash(coords) as f32 / 255.0
+  }
+}
+
+const GRADIENTS_2D: [(f32, f32); 8] = [
+  (1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0),
+  (1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0),
+];
+
+const GRADIENTS_3D: [(f32, f32, f32); 12] = [
+  (1.0, 1.0, 0.0), (-1.0, 1.0, 0.0), (1.0, -1.0, 0.0), (-1.0, -1.0, 0.0),
+  (1.0, 0.0, 1.0), (-1.0, 0.0, 1.0), (1.0, 0.0, -1.0), (-1.0, 0.0, -1.0),
+  (0.0, 1.0, 1.0), (0.0, -1.0, 1.0), (0.0, 1.0, -1.0), (0.0, -1.0, -1.0),
+];
+
+// cos(7.5°), cos(22.5°) .. cos(82.5°): the first quadrant of
+// OpenSimplex2's 24 evenly spaced 2D gradients.
+const OPEN_SIMPLEX_COS: [f32; 6] = [
+  0.991_444_9, 0.923_879_5, 0.793_353_3, 0.608_761_4, 0.382_683_4,
+  0.130_526_2,
+];
+
+fn open_simplex_gradient2(hash: usize) -> (f32, f32) {
+  let (step, quadrant) = (hash % 6, hash / 6 % 4);
+  let (x, y) = (OPEN_SIMPLEX_COS[step], OPEN_SIMPLEX_COS[5 - step]);
+  match quadrant {
+    0 => (x, y),
+    1 => (-y, x),
+    2 => (-x, -y),
+    _ => (y, -x),
+  }
+}
+
+// OpenSimplex2's 48 3D gradients: 16 around each pair of axes, pointing
+// away from the cube's edges and corners.
+fn open_simplex_gradient3(hash: usize) -> (f32, f32, f32) {
+  let hash = hash % 48;
+  let sa = if hash & 1 == 0 { 1.0 } else { -1.0 };
+  let sb = if hash & 2 == 0 { 1.0 } else { -1.0 };
+  let (a, b, c) = match (hash >> 2) & 3 {
+    0 => (2.224_744_9 * sa, 2.224_744_9 * sb, -1.0),
+    1 => (2.224_744_9 * sa, 2.224_744_9 * sb, 1.0),
+    2 => (3.086_266_5 * sa, 1.172_151_3 * sb, 0.0),
+    _ => (1.172_151_3 * sa, 3.086_266_5 * sb, 0.0),
+  };
+  match hash / 16 {
+    0 => (a, b, c),
+    1 => (c, a, b),
+    _ => (b, c, a),
+  }
+}
+
+fn fade(t: f32) -> f32 {
+  t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+  a + t * (b - a)
+}
+
+fn fbm(fractal: &Fractal, mut sample: impl FnMut(f32) -> f32) -> f32 {
+  let mut total = 0.0;
+  let mut amplitude = 1.0;
+  let mut max_amplitude = 0.0;
+  let mut frequency = fractal.frequency;
+  for _ in 0..fractal.octaves.max(1) {
+    total += sample(frequency) * amplitude;
+    max_amplitude += amplitude;
+    amplitude *= fractal.gain;
+    frequency *= fractal.lacunarity;
+  }
+  total / max_amplitude
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const TYPES: [NoiseType; 3] =
+    [NoiseType::Perlin, NoiseType::OpenSimplex, NoiseType::Worley];
+
+  fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+    (0..1000).map(|i| {
+      let i = i as f32;
+      (i * 0.37 - 100.0, i * 0.13 + 5.0, i * -0.71)
+    })
+  }
+
+  #[test]
+  fn test_reproducibility() {
+    let (a, b) = (Noise::seeded(1), Noise::seeded(1));
+    let c = Noise::seeded(2);
+    let mut differs = false;
+    for (x, y, z) in points() {
+      for noise_type in TYPES {
+        assert_eq!(a.sample2(noise_type, x, y), b.sample2(noise_type, x, y));
+        assert_eq!(
+          a.sample3(noise_type, x, y, z),
+          b.sample3(noise_type, x, y, z)
+        );
+        differs |= a.sample2(noise_type, x, y) != c.sample2(noise_type, x, y);
+      }
+    }
+    assert!(differs);
+  }
+
+  #[test]
+  fn test_ranges() {
+    let noise = Noise::seeded(1);
+    let fractal = Fractal::default();
+    for (x, y, z) in points() {
+      for noise_type in TYPES {
+        let min = if noise_type == NoiseType::Worley { 0.0 } else { -1.0 };
+        for value in [
+          noise.sample2(noise_type, x, y),
+          noise.sample3(noise_type, x, y, z),
+          noise.fbm2(noise_type, x, y, &fractal),
+          noise.warp3(noise_type, x, y, z, &fractal, 4.0),
+        ] {
+          assert!((min..=1.0).contains(&value), "{noise_type:?} {value}");
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_continuity() {
+    let noise = Noise::seeded(1);
+    for (x, y, z) in points() {
+      for noise_type in TYPES {
+        let a = noise.sample3(noise_type, x, y, z);
+        let b = noise.sample3(noise_type, x + 0.001, y, z);
+        assert!((a - b).abs() < 0.05, "{noise_type:?} jumped");
+      }
+    }
+  }
+
+  #[test]
+  fn test_gradient_noise_is_zero_on_lattice() {
+    let noise = Noise::seeded(1);
+    for x in -5..5 {
+      for y in -5..5 {
+        assert_eq!(noise.perlin2(x as f32, y as f32), 0.0);
+      }
+    }
+  }
+
+  #[test]
+  fn test_huge_coordinates() {
+    let noise = Noise::seeded(1);
+    for value in [1e10, -1e10, i32::MAX as f32, i32::MIN as f32] {
+      for noise_type in TYPES {
+        let min = if noise_type == NoiseType::Worley { 0.0 } else { -1.0 };
+        let sample = noise.sample2(noise_type, value, value);
+        assert!((min..=1.0).contains(&sample), "{noise_type:?} {sample}");
+        let sample = noise.sample3(noise_type, value, value, value);
+        assert!((min..=1.0).contains(&sample), "{noise_type:?} {sample}");
+      }
+    }
+  }
+}
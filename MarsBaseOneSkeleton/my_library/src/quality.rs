@@ -0,0 +1,177 @@
+use crate::RandomNumberGenerator;
+
+// Results further than this many standard deviations from what a
+// perfect generator would give fail (a 0.1% chance, two-sided).
+const CRITICAL_Z: f64 = 3.29;
+
+/// The outcome of one statistical test run by [`quality_tests`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityTest {
+  /// What was tested.
+  pub name: String,
+  /// The raw test statistic (chi-square, correlation coefficient or
+  /// number of runs).
+  pub statistic: f64,
+  /// How many standard deviations the statistic is from its expected
+  /// value. Values near zero are good.
+  pub z_score: f64,
+  /// `true` if `z_score` is within the critical value.
+  pub passed: bool,
+}
+
+impl QualityTest {
+  fn new(name: &str, statistic: f64, z_score: f64) -> Self {
+    Self {
+      name: name.to_string(),
+      statistic,
+      z_score,
+      passed: z_score.abs() < CRITICAL_Z,
+    }
+  }
+}
+
+impl std::fmt::Display for QualityTest {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} {:<36} statistic {:>12.4}  z {:>7.3}",
+      if self.passed { "PASS" } else { "FAIL" },
+      self.name,
+      self.statistic,
+      self.z_score
+    )
+  }
+}
+
+/// Runs a set of statistical tests on a generator's output:
+///
+/// * Chi-square tests that integer and float `range` results are spread
+///   evenly.
+/// * A serial correlation test that each number doesn't predict the
+///   next.
+/// * A runs test that results above and below the middle alternate as
+///   often as they should.
+///
+/// Each test draws `samples` numbers. These tests catch a broken or
+/// badly skewed generator; they aren't a substitute for a full suite
+/// such as TestU01.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::{quality_tests, RandomNumberGenerator};
+/// let mut rng = RandomNumberGenerator::seeded(1);
+/// for test in quality_tests(&mut rng, 10_000) {
+///   println!("{test}");
+/// }
+/// ```
+pub fn quality_tests(
+  rng: &mut RandomNumberGenerator,
+  samples: usize,
+) -> Vec<QualityTest> {
+  vec![
+    chi_square("chi-square, range(0..16)", 16, samples, || {
+      rng.range(0..16_usize)
+    }),
+    chi_square("chi-square, range(1..=6)", 6, samples, || {
+      rng.range(1..=6_usize) - 1
+    }),
+    chi_square("chi-square, range(0.0..1.0) f32", 16, samples, || {
+      (rng.range(0.0..1.0_f32) * 16.0) as usize
+    }),
+    chi_square("chi-square, range(-1e3..1e3) f64", 16, samples, || {
+      ((rng.range(-1000.0..1000.0_f64) + 1000.0) / 125.0) as usize
+    }),
+    serial_correlation(rng, samples),
+    runs(rng, samples),
+  ]
+}
+
+// Pearson's chi-square test: sorts `samples` values into buckets and
+// compares the counts with an even spread.
+fn chi_square(
+  name: &str,
+  buckets: usize,
+  samples: usize,
+  mut sample: impl FnMut() -> usize,
+) -> QualityTest {
+  let mut counts = vec![0_u64; buckets];
+  for _ in 0..samples {
+    counts[sample().min(buckets - 1)] += 1;
+  }
+  let expected = samples as f64 / buckets as f64;
+  let statistic: f64 = counts
+    .iter()
+    .map(|count| (*count as f64 - expected).powi(2) / expected)
+    .sum();
+  // Wilson-Hilferty: the cube root of chi-square over its degrees of
+  // freedom is close to normally distributed.
+  let k = (buckets - 1) as f64;
+  let mean = 1.0 - 2.0 / (9.0 * k);
+  let z_score = ((statistic / k).cbrt() - mean) / (2.0 / (9.0 * k)).sqrt();
+  QualityTest::new(name, statistic, z_score)
+}
+
+// Lag-1 correlation between consecutive numbers. For independent
+// numbers it is close to zero, with a standard error of 1/sqrt(n).
+fn serial_correlation(
+  rng: &mut RandomNumberGenerator,
+  samples: usize,
+) -> QualityTest {
+  let values: Vec<f64> = (0..samples).map(|_| rng.next::<f64>()).collect();
+  let mean = values.iter().sum::<f64>() / samples as f64;
+  let variance: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+  let covariance: f64 = values
+    .windows(2)
+    .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+    .sum();
+  let statistic = covariance / variance;
+  let z_score = statistic * (samples as f64).sqrt();
+  QualityTest::new("serial correlation, next::<f64>()", statistic, z_score)
+}
+
+// Wald-Wolfowitz runs test: counts runs of numbers above and below 0.5,
+// and compares the count with the number expected by chance.
+fn runs(rng: &mut RandomNumberGenerator, samples: usize) -> QualityTest {
+  let above: Vec<bool> =
+    (0..samples).map(|_| rng.next::<f64>() >= 0.5).collect();
+  let runs = 1 + above.windows(2).filter(|pair| pair[0] != pair[1]).count();
+  let n1 = above.iter().filter(|a| **a).count() as f64;
+  let n2 = samples as f64 - n1;
+  let n = samples as f64;
+  let expected = 2.0 * n1 * n2 / n + 1.0;
+  let variance =
+    2.0 * n1 * n2 * (2.0 * n1 * n2 - n) / (n * n * (n - 1.0));
+  let z_score = (runs as f64 - expected) / variance.sqrt();
+  QualityTest::new("runs above/below 0.5", runs as f64, z_score)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Algorithm;
+
+  #[test]
+  fn test_every_algorithm() {
+    for algorithm in Algorithm::ALL {
+      let mut rng = RandomNumberGenerator::with_algorithm(*algorithm, 1);
+      for test in quality_tests(&mut rng, 100_000) {
+        assert!(test.passed, "{algorithm}: {test}");
+      }
+    }
+  }
+
+  #[test]
+  fn test_detects_bias() {
+    let biased = chi_square("biased", 10, 10_000, || 0);
+    assert!(!biased.passed);
+
+    let mut n = 0;
+    let cycling = chi_square("cycling", 10, 10_000, || {
+      n += 1;
+      n % 10
+    });
+    // Too perfect to be random
+    assert!(!cycling.passed);
+  }
+}
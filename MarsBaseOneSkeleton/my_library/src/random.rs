@@ -1,13 +1,10 @@
-use rand::{Rng, SeedableRng, distributions::uniform::{SampleRange, SampleUniform}};
-
-#[cfg(all(not(feature = "pcg"), not(feature = "xorshift")))]
-type RngCore = rand::prelude::StdRng;
-
-#[cfg(feature = "pcg")]
-type RngCore = rand_pcg::Pcg64Mcg;
-
-#[cfg(feature = "xorshift")]
-type RngCore = rand_xorshift::XorShiftRng;
+use rand::{Rng, distributions::uniform::{SampleRange, SampleUniform}};
+use rand::seq::{IteratorRandom, SliceRandom};
+use crate::{Algorithm, DiceExpression, DiceRoll, Rect2D, RngState};
+use crate::backend::{derive_seed, Backend};
+use crate::{distributions, geometry};
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
 
 //START: rngstruct
 /// `RandomNumberGenerator` holds random number generation state, and offers 
@@ -15,8 +12,8 @@ type RngCore = rand_xorshift::XorShiftRng;
 /// 
 /// `RandomNumberGenerator` defaults to using the 
 /// [PCG](https://crates.io/crates/rand_pcg)<callout id="docs.link" />
-/// algorithm. You can specify `xorshift` as a feature flag to use it 
-/// instead.
+/// algorithm. Use [`RandomNumberGenerator::with_algorithm`] to pick
+/// another [`Algorithm`] at runtime.
 /// 
 /// By default, `RandomNumberGenerator` requires mutability---it 
 /// is shared in Bevy with `ResMut<RandomNumberGenerator>`. If 
@@ -36,7 +33,25 @@ type RngCore = rand_xorshift::XorShiftRng;
 //END: rngstruct
 #[derive(bevy::prelude::Resource)]
 pub struct RandomNumberGenerator {
-  pub rng: RngCore,
+  rng: Backend,
+  seed: u64,
+}
+
+impl Serialize for RandomNumberGenerator {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    self.snapshot().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for RandomNumberGenerator {
+  fn deserialize<D: serde::Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    RngState::deserialize(deserializer).map(Self::from_snapshot)
+  }
 }
 
 impl Default for RandomNumberGenerator {
@@ -50,9 +65,7 @@ impl RandomNumberGenerator {
   /// Creates a default `RandomNumberGenerator`, with a randomly 
   /// selected starting seed.
   pub fn new() -> Self {
-    Self {
-      rng: RngCore::from_entropy(),
-    }
+    Self::seeded(rand::random())
   }
   //END: rng.new
 
@@ -74,11 +87,100 @@ impl RandomNumberGenerator {
   /// assert_eq!(results.0, results.1);
   /// ```
   pub fn seeded(seed: u64) -> Self {
+    Self::with_algorithm(Algorithm::default(), seed)
+  }
+  //END: rng.seeded
+
+  /// Creates a new `RandomNumberGenerator` that uses a specific
+  /// algorithm. Streams and forks derived from it use the same
+  /// algorithm.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use my_library_mbone_skeleton::{Algorithm, RandomNumberGenerator};
+  /// for algorithm in Algorithm::ALL {
+  ///   let mut rng = RandomNumberGenerator::with_algorithm(*algorithm, 1);
+  ///   assert_eq!(rng.algorithm(), *algorithm);
+  ///   println!("{algorithm}: {}", rng.range(1..=6));
+  /// }
+  /// ```
+  pub fn with_algorithm(algorithm: Algorithm, seed: u64) -> Self {
     Self {
-      rng: RngCore::seed_from_u64(seed),
+      rng: Backend::seeded(algorithm, seed),
+      seed,
+    }
+  }
+
+  /// The algorithm this generator uses.
+  pub fn algorithm(&self) -> Algorithm {
+    self.rng.algorithm()
+  }
+
+  /// The seed this generator (or its snapshot) was created with.
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  /// Derives a named child generator from this generator's seed. The
+  /// child doesn't consume any numbers from its parent, so giving each
+  /// subsystem (world generation, particles, AI) its own stream keeps
+  /// them from disturbing each other's sequences, while one seed still
+  /// reproduces the whole run.
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - the stream to create. The same seed and name always
+  ///   produce the same stream.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use my_library_mbone_skeleton::RandomNumberGenerator;
+  /// let mut master = RandomNumberGenerator::seeded(1);
+  /// let mut world = master.stream("world");
+  /// master.next::<u32>();
+  /// assert_eq!(world.next::<u32>(), master.stream("world").next::<u32>());
+  /// ```
+  pub fn stream(&self, name: &str) -> Self {
+    Self::with_algorithm(self.algorithm(), derive_seed(self.seed, name))
+  }
+
+  /// Derives a child generator from this generator's *current* state,
+  /// advancing the parent. Forking repeatedly with the same label gives
+  /// different (but reproducible) children, which is useful for things
+  /// like generating a new level each time a game starts.
+  ///
+  /// # Arguments
+  ///
+  /// * `label` - distinguishes children forked from the same state.
+  pub fn fork(&mut self, label: &str) -> Self {
+    let seed = derive_seed(self.next(), label);
+    Self::with_algorithm(self.algorithm(), seed)
+  }
+
+  /// Captures the generator's current state. Restoring the snapshot
+  /// (with [`RandomNumberGenerator::restore`] or
+  /// [`RandomNumberGenerator::from_snapshot`]) resumes the exact same
+  /// random sequence.
+  pub fn snapshot(&self) -> RngState {
+    RngState::new(self.seed, self.rng.clone())
+  }
+
+  /// Rewinds (or fast-forwards) the generator to a previously captured
+  /// state.
+  pub fn restore(&mut self, state: RngState) {
+    self.rng = state.core;
+    self.seed = state.seed;
+  }
+
+  /// Creates a `RandomNumberGenerator` from a previously captured state.
+  pub fn from_snapshot(state: RngState) -> Self {
+    Self {
+      rng: state.core,
+      seed: state.seed,
     }
   }
-  //END: rng.seeded
 
   //START: rng.next_range
   /// Generates a new random number of the requested type.
@@ -110,6 +212,203 @@ impl RandomNumberGenerator {
     self.rng.gen_range(range)
   }
   //END: rng.next_range
+
+  /// Returns `true` with the given probability: `rng.chance(0.01)` is
+  /// true about 1% of the time. Probabilities outside `0.0..=1.0` are
+  /// clamped, and a NaN probability is never true.
+  pub fn chance(&mut self, probability: f64) -> bool {
+    distributions::chance(&mut self.rng, probability)
+  }
+
+  /// Samples a normal (gaussian, "bell curve") distribution.
+  ///
+  /// # Arguments
+  ///
+  /// * `mean` - the center of the distribution.
+  /// * `std_dev` - the standard deviation; about 68% of results fall
+  ///   within `std_dev` of the mean.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `std_dev` isn't finite.
+  pub fn normal(&mut self, mean: f32, std_dev: f32) -> f32 {
+    distributions::normal(&mut self.rng, mean, std_dev)
+  }
+
+  /// Samples an exponential distribution, such as the time between
+  /// randomly occurring events.
+  ///
+  /// # Arguments
+  ///
+  /// * `rate` - the average number of events per unit of time. The mean
+  ///   result is `1.0 / rate`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `rate` is negative or NaN.
+  pub fn exponential(&mut self, rate: f32) -> f32 {
+    distributions::exponential(&mut self.rng, rate)
+  }
+
+  /// Samples a Poisson distribution: how many randomly occurring events
+  /// happen in a unit of time.
+  ///
+  /// # Arguments
+  ///
+  /// * `mean` - the average number of events.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `mean` isn't positive and finite.
+  pub fn poisson(&mut self, mean: f64) -> u64 {
+    distributions::poisson(&mut self.rng, mean)
+  }
+
+  /// Samples a binomial distribution: how many of `trials` attempts
+  /// succeed, when each succeeds with `probability`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `probability` isn't within `0.0..=1.0`.
+  pub fn binomial(&mut self, trials: u64, probability: f64) -> u64 {
+    distributions::binomial(&mut self.rng, trials, probability)
+  }
+
+  /// Samples a triangular distribution between `min` and `max`, with
+  /// results clustered around `mode`.
+  ///
+  /// # Panics
+  ///
+  /// Panics unless `min <= mode <= max`.
+  pub fn triangular(&mut self, min: f32, max: f32, mode: f32) -> f32 {
+    distributions::triangular(&mut self.rng, min, max, mode)
+  }
+
+  /// Picks a point inside a circle, with every part of the circle
+  /// equally likely.
+  pub fn point_in_circle(&mut self, center: Vec2, radius: f32) -> Vec2 {
+    geometry::point_in_circle(&mut self.rng, center, radius)
+  }
+
+  /// Picks a point on a ring between two radii, with every part of the
+  /// ring equally likely. If the radii are equal, the point is on the
+  /// circle itself: `rng.point_on_ring(Vec2::ZERO, 1.0, 1.0)` is a
+  /// random direction.
+  pub fn point_on_ring(
+    &mut self,
+    center: Vec2,
+    inner_radius: f32,
+    outer_radius: f32,
+  ) -> Vec2 {
+    geometry::point_on_ring(&mut self.rng, center, inner_radius, outer_radius)
+  }
+
+  /// Picks a point inside a rectangle.
+  pub fn point_in_rect(&mut self, rect: &Rect2D) -> Vec2 {
+    geometry::point_in_rect(&mut self.rng, rect)
+  }
+
+  /// Picks a point on the line segment between `start` and `end`.
+  pub fn point_on_segment(&mut self, start: Vec2, end: Vec2) -> Vec2 {
+    geometry::point_on_segment(&mut self.rng, start, end)
+  }
+
+  /// Parses and rolls a dice-notation expression (see
+  /// [`DiceExpression`] for the supported syntax), returning the total
+  /// and each individual die.
+  ///
+  /// # Arguments
+  ///
+  /// * `expression` - the dice to roll, e.g. `3d6+2` or `4d6kh3`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use my_library_mbone_skeleton::RandomNumberGenerator;
+  /// let mut rng = RandomNumberGenerator::new();
+  /// let roll = rng.roll_dice("3d6+2").unwrap();
+  /// assert!(roll.total >= 5 && roll.total <= 20);
+  /// assert_eq!(roll.rolls.len(), 3);
+  /// ```
+  pub fn roll_dice(&mut self, expression: &str) -> anyhow::Result<DiceRoll> {
+    Ok(DiceExpression::parse(expression)?.roll(&mut self.rng))
+  }
+
+  /// Rolls a pre-parsed [`DiceExpression`].
+  pub fn roll(&mut self, expression: &DiceExpression) -> DiceRoll {
+    expression.roll(&mut self.rng)
+  }
+
+  /// Shuffles a slice in place.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use my_library_mbone_skeleton::RandomNumberGenerator;
+  /// let mut rng = RandomNumberGenerator::seeded(1);
+  /// let mut deck: Vec<u32> = (1..=52).collect();
+  /// rng.shuffle(&mut deck);
+  /// assert_eq!(deck.len(), 52);
+  /// ```
+  pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+    slice.shuffle(&mut self.rng);
+  }
+
+  /// Picks one element of a slice. Returns `None` if the slice is empty.
+  pub fn choose<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+    slice.choose(&mut self.rng)
+  }
+
+  /// Picks `amount` distinct elements of a slice, in random order. If
+  /// the slice is shorter than `amount`, every element is returned.
+  pub fn choose_multiple<'a, T>(
+    &mut self,
+    slice: &'a [T],
+    amount: usize,
+  ) -> Vec<&'a T> {
+    slice.choose_multiple(&mut self.rng, amount).collect()
+  }
+
+  /// Picks `amount` distinct indices from `0..length`, in random order.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `amount` is greater than `length`.
+  pub fn sample_indices(&mut self, length: usize, amount: usize) -> Vec<usize> {
+    rand::seq::index::sample(&mut self.rng, length, amount).into_vec()
+  }
+
+  /// Picks `amount` items from an iterator of any (or unknown) length
+  /// with reservoir sampling, visiting each item once. The items are
+  /// returned in no particular order. If the iterator yields fewer than
+  /// `amount` items, every item is returned.
+  pub fn reservoir_sample<I: IntoIterator>(
+    &mut self,
+    items: I,
+    amount: usize,
+  ) -> Vec<I::Item> {
+    items.into_iter().choose_multiple(&mut self.rng, amount)
+  }
+}
+
+/// `RandomNumberGenerator` can be passed to anything in the `rand`
+/// ecosystem that expects a generator.
+impl rand::RngCore for RandomNumberGenerator {
+  fn next_u32(&mut self) -> u32 {
+    rand::RngCore::next_u32(&mut self.rng)
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    rand::RngCore::next_u64(&mut self.rng)
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    rand::RngCore::fill_bytes(&mut self.rng, dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    rand::RngCore::try_fill_bytes(&mut self.rng, dest)
+  }
 }
 
 #[cfg(test)]
@@ -157,6 +456,129 @@ mod test {
     let _ = rng.next::<f32>();
   }
 
+  #[test]
+  fn test_roll_dice() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    for _ in 0..1000 {
+      let roll = rng.roll_dice("2d6").unwrap();
+      assert!((2..=12).contains(&roll.total));
+    }
+    assert!(rng.roll_dice("2d").is_err());
+  }
+
+  #[test]
+  fn test_snapshot() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    rng.range(0..100);
+    let bytes = rng.snapshot().to_bytes().unwrap();
+    let expected: Vec<u32> = (0..100).map(|_| rng.next()).collect();
+
+    let mut restored = RandomNumberGenerator::new();
+    restored.restore(RngState::from_bytes(&bytes).unwrap());
+    let actual: Vec<u32> = (0..100).map(|_| restored.next()).collect();
+    assert_eq!(expected, actual);
+    assert!(RngState::from_bytes(&bytes[1..]).is_err());
+  }
+
+  #[test]
+  fn test_serde() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let bytes = bincode::serialize(&rng).unwrap();
+    let mut restored: RandomNumberGenerator =
+      bincode::deserialize(&bytes).unwrap();
+    assert_eq!(rng.next::<u64>(), restored.next::<u64>());
+  }
+
+  #[test]
+  fn test_streams() {
+    let mut master = RandomNumberGenerator::seeded(1);
+    let mut world = master.stream("world");
+    let mut particles = master.stream("particles");
+    let first: Vec<u32> = (0..10).map(|_| world.next()).collect();
+
+    // Using the master or another stream doesn't change a stream
+    (0..10).for_each(|_| { master.next::<u32>(); particles.next::<u32>(); });
+    let mut world = master.stream("world");
+    let second: Vec<u32> = (0..10).map(|_| world.next()).collect();
+    assert_eq!(first, second);
+
+    let mut particles = master.stream("particles");
+    let other: Vec<u32> = (0..10).map(|_| particles.next()).collect();
+    assert_ne!(first, other);
+  }
+
+  #[test]
+  fn test_fork() {
+    let mut rng = (
+      RandomNumberGenerator::seeded(1),
+      RandomNumberGenerator::seeded(1),
+    );
+    let mut a = rng.0.fork("level");
+    let mut b = rng.0.fork("level");
+    assert_ne!(a.next::<u64>(), b.next::<u64>());
+    let mut a2 = rng.1.fork("level");
+    assert_eq!(a.seed(), a2.seed());
+    assert_eq!(a.next::<u64>(), { a2.next::<u64>(); a2.next::<u64>() });
+  }
+
+  #[test]
+  fn test_sequences() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let mut deck: Vec<u32> = (0..52).collect();
+    rng.shuffle(&mut deck);
+    assert_ne!(deck, (0..52).collect::<Vec<u32>>());
+    deck.sort();
+    assert_eq!(deck, (0..52).collect::<Vec<u32>>());
+
+    assert!(deck.contains(rng.choose(&deck).unwrap()));
+    assert!(rng.choose::<u32>(&[]).is_none());
+
+    let mut picked = rng.choose_multiple(&deck, 5);
+    picked.sort();
+    picked.dedup();
+    assert_eq!(picked.len(), 5);
+    assert_eq!(rng.choose_multiple(&deck, 100).len(), 52);
+
+    let mut indices = rng.sample_indices(10, 10);
+    indices.sort();
+    assert_eq!(indices, (0..10).collect::<Vec<usize>>());
+
+    let sample = rng.reservoir_sample((0..1000).filter(|n| n % 2 == 0), 3);
+    assert_eq!(sample.len(), 3);
+    assert!(sample.iter().all(|n| n % 2 == 0));
+    assert_eq!(rng.reservoir_sample(0..2, 3).len(), 2);
+  }
+
+  #[test]
+  fn test_rng_core() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let mut expected = RandomNumberGenerator::seeded(1);
+    assert_eq!(rand::RngCore::next_u64(&mut rng), expected.next::<u64>());
+    let mut items = [1, 2, 3];
+    items.shuffle(&mut rng);
+    assert!(items.contains(&1));
+  }
+
+  #[test]
+  fn test_algorithms() {
+    for algorithm in Algorithm::ALL {
+      let mut rng = RandomNumberGenerator::with_algorithm(*algorithm, 1);
+      assert_eq!(rng.stream("world").algorithm(), *algorithm);
+      assert_eq!(rng.fork("level").algorithm(), *algorithm);
+
+      let state = rng.snapshot();
+      assert_eq!(state.algorithm(), *algorithm);
+      let bytes = state.to_bytes().unwrap();
+      let mut restored = RandomNumberGenerator::seeded(2);
+      restored.restore(RngState::from_bytes(&bytes).unwrap());
+      assert_eq!(restored.algorithm(), *algorithm);
+      assert_eq!(rng.next::<u64>(), restored.next::<u64>());
+
+      assert_eq!(algorithm.name().parse::<Algorithm>().unwrap(), *algorithm);
+    }
+    assert!("mersenne".parse::<Algorithm>().is_err());
+  }
+
   #[test]
   fn test_float() {
     let mut rng = RandomNumberGenerator::new();
@@ -170,19 +592,3 @@ mod test {
     }
   }
 }
-
-//START: random_plugin
-/// `Random` is a Bevy plugin that inserts a `RandomNumberGenerator` 
-/// resource into your application.
-/// 
-/// Once you add the plugin (with `App::new().add_plugin(Random)`),
-/// you can access a random number generator in systems with
-/// `rng: ResMut<RandomNumberGenerator>`.
-pub struct RandomPlugin;
-//END: random_plugin
-
-impl bevy::prelude::Plugin for RandomPlugin {
-  fn build(&self, app: &mut bevy::prelude::App) {
-      app.insert_resource(RandomNumberGenerator::new());
-  }
-}
\ No newline at end of file
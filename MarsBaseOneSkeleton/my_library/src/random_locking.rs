@@ -1,18 +1,74 @@
-use rand::{Rng, SeedableRng, distributions::uniform::{SampleRange, SampleUniform}};
-use std::sync::Mutex;
+use rand::{Rng, distributions::uniform::{SampleRange, SampleUniform}};
+use rand::seq::{IteratorRandom, SliceRandom};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::ThreadId;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use crate::{Algorithm, DiceExpression, DiceRoll, Rect2D, RngState};
+use crate::backend::{derive_seed, Backend};
+use crate::{distributions, geometry};
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
 
-#[cfg(all(not(feature = "pcg"), not(feature = "xorshift")))]
-type RngCore = rand::prelude::StdRng;
+// The number of generators each `RandomNumberGenerator` keeps. It's
+// fixed (rather than based on the CPU count) so that a seed produces the
+// same sequences on every machine.
+const SHARDS: usize = 16;
 
-#[cfg(feature = "pcg")]
-type RngCore = rand_pcg::Pcg64Mcg;
+// Shard 0, which is seeded exactly like the non-locking generator,
+// belongs to the thread that created the generator. Every other thread
+// is given one of the remaining shards the first time it needs one, so
+// which shard that is depends on the order threads start drawing
+// numbers, which changes from run to run.
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
 
-#[cfg(feature = "xorshift")]
-type RngCore = rand_xorshift::XorShiftRng;
+thread_local! {
+  static SHARD: usize =
+    1 + NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % (SHARDS - 1);
+  // `thread::current()` clones an `Arc`, which is slow enough to show
+  // up when every draw checks it.
+  static THREAD: ThreadId = std::thread::current().id();
+}
+
+// Each shard sits on its own cache line, so threads using neighbouring
+// shards don't slow each other down.
+#[repr(align(64))]
+struct Shard(Mutex<Backend>);
 
+/// Rather than sharing one generator behind a single lock, the
+/// generator keeps a small set of generators (shards), all derived from
+/// the seed. Each thread draws from its own shard, so parallel systems
+/// sharing `Res<RandomNumberGenerator>` almost never wait on each other.
+///
+/// Numbers drawn on the thread that created the generator (usually the
+/// main thread) follow the seed just like the non-locking generator.
+/// Numbers drawn on any other thread are *not* reproducible: which shard
+/// a thread is given depends on thread scheduling, and Bevy runs a
+/// system on different threads from frame to frame. For reproducible
+/// runs, give each system its own stream (see
+/// [`RandomStreams`](crate::RandomStreams)), or build without the
+/// `locking` feature.
 #[derive(bevy::prelude::Resource)]
 pub struct RandomNumberGenerator {
-  pub rng: Mutex<RngCore>,
+  shards: Box<[Shard]>,
+  seed: AtomicU64,
+  owner: ThreadId,
+}
+
+impl Serialize for RandomNumberGenerator {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    self.snapshot().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for RandomNumberGenerator {
+  fn deserialize<D: serde::Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    RngState::deserialize(deserializer).map(Self::from_snapshot)
+  }
 }
 
 impl Default for RandomNumberGenerator {
@@ -23,21 +79,78 @@ impl Default for RandomNumberGenerator {
 
 impl RandomNumberGenerator {
   pub fn new() -> Self {
-    Self {
-      rng: Mutex::new(RngCore::from_entropy()),
-    }
+    Self::seeded(rand::random())
   }
 
   pub fn seeded(seed: u64) -> Self {
+    Self::with_algorithm(Algorithm::default(), seed)
+  }
+
+  pub fn with_algorithm(algorithm: Algorithm, seed: u64) -> Self {
+    Self::from_snapshot(RngState::new(seed, Backend::seeded(algorithm, seed)))
+  }
+
+  pub fn algorithm(&self) -> Algorithm {
+    self.shards[0].0.lock().unwrap().algorithm()
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed.load(Ordering::Relaxed)
+  }
+
+  pub fn stream(&self, name: &str) -> Self {
+    Self::with_algorithm(self.algorithm(), derive_seed(self.seed(), name))
+  }
+
+  pub fn fork(&self, label: &str) -> Self {
+    let seed = derive_seed(self.next(), label);
+    Self::with_algorithm(self.algorithm(), seed)
+  }
+
+  pub fn snapshot(&self) -> RngState {
+    let mut cores = self
+      .shards
+      .iter()
+      .map(|shard| shard.0.lock().unwrap().clone());
+    let mut state = RngState::new(self.seed(), cores.next().unwrap());
+    state.shards = cores.collect();
+    state
+  }
+
+  pub fn restore(&self, state: RngState) {
+    let seed = state.seed;
+    let mut cores = shard_cores(state).into_iter();
+    for shard in self.shards.iter() {
+      *shard.0.lock().unwrap() = cores.next().unwrap();
+    }
+    self.seed.store(seed, Ordering::Relaxed);
+  }
+
+  pub fn from_snapshot(state: RngState) -> Self {
     Self {
-      rng: Mutex::new(RngCore::seed_from_u64(seed)),
+      seed: AtomicU64::new(state.seed),
+      owner: std::thread::current().id(),
+      shards: shard_cores(state)
+        .into_iter()
+        .map(|core| Shard(Mutex::new(core)))
+        .collect(),
     }
   }
 
+  // Locks the calling thread's shard.
+  fn lock(&self) -> MutexGuard<'_, Backend> {
+    let index = if THREAD.with(|thread| *thread) == self.owner {
+      0
+    } else {
+      SHARD.with(|shard| *shard)
+    };
+    self.shards[index].0.lock().unwrap()
+  }
+
   pub fn next<T>(&self) -> T
   where rand::distributions::Standard: rand::prelude::Distribution<T>
   {
-    let mut lock = self.rng.lock().unwrap();
+    let mut lock = self.lock();
     lock.gen()
   }
 
@@ -45,9 +158,160 @@ impl RandomNumberGenerator {
   where
     T: SampleUniform + PartialOrd,
   {
-    let mut lock = self.rng.lock().unwrap();
+    let mut lock = self.lock();
     lock.gen_range(range)
   }
+
+  pub fn chance(&self, probability: f64) -> bool {
+    let mut lock = self.lock();
+    distributions::chance(&mut *lock, probability)
+  }
+
+  pub fn normal(&self, mean: f32, std_dev: f32) -> f32 {
+    let mut lock = self.lock();
+    distributions::normal(&mut *lock, mean, std_dev)
+  }
+
+  pub fn exponential(&self, rate: f32) -> f32 {
+    let mut lock = self.lock();
+    distributions::exponential(&mut *lock, rate)
+  }
+
+  pub fn poisson(&self, mean: f64) -> u64 {
+    let mut lock = self.lock();
+    distributions::poisson(&mut *lock, mean)
+  }
+
+  pub fn binomial(&self, trials: u64, probability: f64) -> u64 {
+    let mut lock = self.lock();
+    distributions::binomial(&mut *lock, trials, probability)
+  }
+
+  pub fn triangular(&self, min: f32, max: f32, mode: f32) -> f32 {
+    let mut lock = self.lock();
+    distributions::triangular(&mut *lock, min, max, mode)
+  }
+
+  pub fn point_in_circle(&self, center: Vec2, radius: f32) -> Vec2 {
+    let mut lock = self.lock();
+    geometry::point_in_circle(&mut *lock, center, radius)
+  }
+
+  pub fn point_on_ring(
+    &self,
+    center: Vec2,
+    inner_radius: f32,
+    outer_radius: f32,
+  ) -> Vec2 {
+    let mut lock = self.lock();
+    geometry::point_on_ring(&mut *lock, center, inner_radius, outer_radius)
+  }
+
+  pub fn point_in_rect(&self, rect: &Rect2D) -> Vec2 {
+    let mut lock = self.lock();
+    geometry::point_in_rect(&mut *lock, rect)
+  }
+
+  pub fn point_on_segment(&self, start: Vec2, end: Vec2) -> Vec2 {
+    let mut lock = self.lock();
+    geometry::point_on_segment(&mut *lock, start, end)
+  }
+
+  pub fn roll_dice(&self, expression: &str) -> anyhow::Result<DiceRoll> {
+    Ok(self.roll(&DiceExpression::parse(expression)?))
+  }
+
+  pub fn roll(&self, expression: &DiceExpression) -> DiceRoll {
+    let mut lock = self.lock();
+    expression.roll(&mut *lock)
+  }
+
+  pub fn shuffle<T>(&self, slice: &mut [T]) {
+    let mut lock = self.lock();
+    slice.shuffle(&mut *lock);
+  }
+
+  pub fn choose<'a, T>(&self, slice: &'a [T]) -> Option<&'a T> {
+    let mut lock = self.lock();
+    slice.choose(&mut *lock)
+  }
+
+  pub fn choose_multiple<'a, T>(
+    &self,
+    slice: &'a [T],
+    amount: usize,
+  ) -> Vec<&'a T> {
+    let mut lock = self.lock();
+    slice.choose_multiple(&mut *lock, amount).collect()
+  }
+
+  pub fn sample_indices(&self, length: usize, amount: usize) -> Vec<usize> {
+    let mut lock = self.lock();
+    rand::seq::index::sample(&mut *lock, length, amount).into_vec()
+  }
+
+  pub fn reservoir_sample<I: IntoIterator>(
+    &self,
+    items: I,
+    amount: usize,
+  ) -> Vec<I::Item> {
+    // The iterator may itself use this generator, so the shard is
+    // locked for each draw rather than for the whole walk.
+    items.into_iter().choose_multiple(&mut &*self, amount)
+  }
+}
+
+impl rand::RngCore for RandomNumberGenerator {
+  fn next_u32(&mut self) -> u32 {
+    rand::RngCore::next_u32(&mut &*self)
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    rand::RngCore::next_u64(&mut &*self)
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    rand::RngCore::fill_bytes(&mut &*self, dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    rand::RngCore::try_fill_bytes(&mut &*self, dest)
+  }
+}
+
+// Lets a shared `Res<RandomNumberGenerator>` be used as a `rand`
+// generator, with `&mut &*rng`.
+impl rand::RngCore for &RandomNumberGenerator {
+  fn next_u32(&mut self) -> u32 {
+    rand::RngCore::next_u32(&mut *self.lock())
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    rand::RngCore::next_u64(&mut *self.lock())
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    rand::RngCore::fill_bytes(&mut *self.lock(), dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    rand::RngCore::try_fill_bytes(&mut *self.lock(), dest)
+  }
+}
+
+// Expands a snapshot into one generator per shard. Snapshots taken by the
+// non-locking generator only carry the first, so the rest are derived
+// from the seed.
+fn shard_cores(state: RngState) -> Vec<Backend> {
+  let algorithm = state.core.algorithm();
+  let mut cores = Vec::with_capacity(SHARDS);
+  cores.push(state.core);
+  cores.extend(state.shards.into_iter().take(SHARDS - 1));
+  for index in cores.len()..SHARDS {
+    let seed = derive_seed(state.seed, &format!("shard {index}"));
+    cores.push(Backend::seeded(algorithm, seed));
+  }
+  cores
 }
 
 #[cfg(test)]
@@ -95,6 +359,92 @@ mod test {
     let _ = rng.next::<f32>();
   }
 
+  #[test]
+  fn test_roll_dice() {
+    let rng = RandomNumberGenerator::seeded(1);
+    for _ in 0..1000 {
+      let roll = rng.roll_dice("2d6").unwrap();
+      assert!((2..=12).contains(&roll.total));
+    }
+    assert!(rng.roll_dice("2d").is_err());
+  }
+
+  #[test]
+  fn test_snapshot() {
+    let rng = RandomNumberGenerator::seeded(1);
+    rng.range(0..100);
+    let bytes = rng.snapshot().to_bytes().unwrap();
+    let expected: Vec<u32> = (0..100).map(|_| rng.next()).collect();
+
+    let restored = RandomNumberGenerator::new();
+    restored.restore(RngState::from_bytes(&bytes).unwrap());
+    let actual: Vec<u32> = (0..100).map(|_| restored.next()).collect();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn test_streams() {
+    let master = RandomNumberGenerator::seeded(1);
+    let world = master.stream("world");
+    let first: Vec<u32> = (0..10).map(|_| world.next()).collect();
+    (0..10).for_each(|_| { master.next::<u32>(); });
+    let world = master.stream("world");
+    let second: Vec<u32> = (0..10).map(|_| world.next()).collect();
+    assert_eq!(first, second);
+  }
+
+  #[test]
+  fn test_threads() {
+    let rng = RandomNumberGenerator::seeded(1);
+    std::thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          assert!((0..1000).all(|_| (1..=6).contains(&rng.range(1..=6))));
+        });
+      }
+    });
+
+    // However the threads drew numbers, streams still follow the seed
+    let expected = RandomNumberGenerator::seeded(1).stream("world");
+    let world = rng.stream("world");
+    let expected: Vec<u32> = (0..100).map(|_| expected.next()).collect();
+    let actual: Vec<u32> = (0..100).map(|_| world.next()).collect();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn test_owner_follows_seed() {
+    let rng = RandomNumberGenerator::seeded(1);
+    let expected: Vec<u32> = (0..1000).map(|_| rng.next()).collect();
+
+    // Other threads drawing at the same time use their own shards, and
+    // don't disturb the sequence of the thread that made the generator
+    let rng = RandomNumberGenerator::seeded(1);
+    let actual = std::thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| (0..1000).for_each(|_| { rng.next::<u32>(); }));
+      }
+      (0..1000).map(|_| rng.next()).collect::<Vec<u32>>()
+    });
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn test_sequences() {
+    let rng = RandomNumberGenerator::seeded(1);
+    let mut deck: Vec<u32> = (0..52).collect();
+    rng.shuffle(&mut deck);
+    deck.sort();
+    assert_eq!(deck, (0..52).collect::<Vec<u32>>());
+    assert!(deck.contains(rng.choose(&deck).unwrap()));
+    assert_eq!(rng.choose_multiple(&deck, 5).len(), 5);
+    assert_eq!(rng.sample_indices(10, 3).len(), 3);
+
+    // The iterator draws from the same generator while it is sampled
+    let sample = rng.reservoir_sample((0..100).map(|_| rng.range(0..10)), 3);
+    assert_eq!(sample.len(), 3);
+  }
+
   #[test]
   fn test_float() {
     let rng = RandomNumberGenerator::new();
@@ -108,11 +458,3 @@ mod test {
     }
   }
 }
-
-pub struct RandomPlugin;
-
-impl bevy::prelude::Plugin for RandomPlugin {
-  fn build(&self, app: &mut bevy::prelude::App) {
-      app.insert_resource(RandomNumberGenerator::new());
-  }
-}
\ No newline at end of file
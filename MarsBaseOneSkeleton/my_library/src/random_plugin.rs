@@ -0,0 +1,179 @@
+use crate::{Algorithm, RandomNumberGenerator, RandomStreams};
+use bevy::{prelude::*, log};
+use std::ffi::OsString;
+
+/// The environment variable `RandomPlugin::new` reads a seed from.
+pub const SEED_ENV_VAR: &str = "RNG_SEED";
+
+/// The command-line argument `RandomPlugin::new` reads a seed from,
+/// as `--seed 1234` or `--seed=1234`.
+pub const SEED_ARG: &str = "--seed";
+
+/// The seed used by the `RandomNumberGenerator` (and `RandomStreams`)
+/// resources. `RandomPlugin` inserts it, and logs it at startup so that
+/// a run can be reproduced.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomSeed(pub u64);
+
+//START: random_plugin
+/// `RandomPlugin` is a Bevy plugin that inserts a `RandomNumberGenerator`
+/// resource into your application.
+///
+/// Once you add the plugin (with
+/// `App::new().add_plugins(RandomPlugin::new())`),
+/// you can access a random number generator in systems with
+/// `rng: ResMut<RandomNumberGenerator>`. Named streams derived from the
+/// same seed are available with `streams: ResMut<RandomStreams>`, and
+/// the seed itself with `Res<RandomSeed>`.
+///
+/// The seed is chosen, in order of preference, from:
+///
+/// * A seed passed to [`RandomPlugin::seeded`].
+/// * The `--seed` command-line argument (`--seed 1234`).
+/// * The `RNG_SEED` environment variable.
+/// * A random seed.
+pub struct RandomPlugin {
+  seed: Option<u64>,
+  algorithm: Algorithm,
+}
+//END: random_plugin
+
+impl Default for RandomPlugin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl RandomPlugin {
+  /// Creates a plugin that reads its seed from the command line or the
+  /// environment, or picks a random seed if neither is set.
+  pub fn new() -> Self {
+    Self {
+      seed: None,
+      algorithm: Algorithm::default(),
+    }
+  }
+
+  /// Creates a plugin that always uses `seed`.
+  pub fn seeded(seed: u64) -> Self {
+    Self {
+      seed: Some(seed),
+      ..Self::new()
+    }
+  }
+
+  /// Selects the random number generation algorithm to use.
+  pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+    self.algorithm = algorithm;
+    self
+  }
+}
+
+impl Plugin for RandomPlugin {
+  fn build(&self, app: &mut App) {
+    let seed = self.seed.unwrap_or_else(|| {
+      let env = std::env::var_os(SEED_ENV_VAR);
+      seed_from(std::env::args_os(), env)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .unwrap_or_else(rand::random)
+    });
+    let rng = RandomNumberGenerator::with_algorithm(self.algorithm, seed);
+    app.insert_resource(RandomStreams::new(&rng));
+    app.insert_resource(rng);
+    app.insert_resource(RandomSeed(seed));
+    app.add_systems(Startup, log_seed);
+  }
+}
+
+fn log_seed(seed: Res<RandomSeed>) {
+  log::info!("Random seed: {} (run with {SEED_ARG} {} to repeat it)",
+    seed.0, seed.0);
+}
+
+// Finds a seed in the command-line arguments, falling back to the
+// environment variable's value. Malformed seeds are errors rather than
+// being ignored, so a run that was meant to be reproduced can't quietly
+// use a random seed instead. Arguments that aren't valid UTF-8 (which
+// `std::env::args` panics on) are converted lossily; they can't be seeds.
+fn seed_from(
+  args: impl Iterator<Item = OsString>,
+  env: Option<OsString>,
+) -> anyhow::Result<Option<u64>> {
+  let lossy = |arg: OsString| arg.to_string_lossy().into_owned();
+  let env = env.map(lossy);
+  let mut args = args.skip(1).map(lossy);
+  while let Some(arg) = args.next() {
+    let value = if arg == SEED_ARG {
+      args.next()
+    } else if let Some(value) = arg.strip_prefix("--seed=") {
+      Some(value.to_string())
+    } else {
+      continue;
+    };
+    let value = value.unwrap_or_default();
+    return value.trim().parse().map(Some).map_err(|_| {
+      anyhow::Error::msg(format!("Invalid {SEED_ARG} argument: \"{value}\""))
+    });
+  }
+  env
+    .map(|value| {
+      value.trim().parse().map_err(|_| {
+        anyhow::Error::msg(format!("Invalid {SEED_ENV_VAR}: \"{value}\""))
+      })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn args(args: &[&str]) -> impl Iterator<Item = OsString> {
+    std::iter::once("game")
+      .chain(args.iter().copied())
+      .map(OsString::from)
+      .collect::<Vec<_>>()
+      .into_iter()
+  }
+
+  #[test]
+  fn test_seed_from() {
+    assert_eq!(seed_from(args(&[]), None).unwrap(), None);
+    assert_eq!(seed_from(args(&["--seed", "42"]), None).unwrap(), Some(42));
+    assert_eq!(seed_from(args(&["-v", "--seed=7"]), None).unwrap(), Some(7));
+    assert_eq!(seed_from(args(&[]), Some("9".into())).unwrap(), Some(9));
+    assert_eq!(
+      seed_from(args(&["--seed", "1"]), Some("9".into())).unwrap(),
+      Some(1)
+    );
+    assert!(seed_from(args(&["--seed"]), None).is_err());
+    assert!(seed_from(args(&["--seed", "abc"]), None).is_err());
+    assert!(seed_from(args(&[]), Some("-1".into())).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_non_utf8_args() {
+    use std::os::unix::ffi::OsStringExt;
+    let invalid = OsString::from_vec(vec![0xff]);
+    let game = OsString::from("game");
+    let args = [game.clone(), invalid.clone(), "--seed".into(), "3".into()];
+    assert_eq!(seed_from(args.into_iter(), None).unwrap(), Some(3));
+    let args = [game, "--seed".into(), invalid.clone()];
+    assert!(seed_from(args.into_iter(), None).is_err());
+    assert!(seed_from(std::iter::empty(), Some(invalid)).is_err());
+  }
+
+  #[test]
+  fn test_plugin_seed() {
+    let mut app = App::new();
+    app.add_plugins(
+      RandomPlugin::seeded(42).with_algorithm(Algorithm::StdRng),
+    );
+    assert_eq!(*app.world().resource::<RandomSeed>(), RandomSeed(42));
+    let rng = app.world().resource::<RandomNumberGenerator>();
+    assert_eq!(rng.seed(), 42);
+    assert_eq!(rng.algorithm(), Algorithm::StdRng);
+    assert_eq!(app.world().resource::<RandomStreams>().seed(), 42);
+  }
+}
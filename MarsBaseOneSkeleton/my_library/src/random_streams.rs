@@ -0,0 +1,52 @@
+use crate::RandomNumberGenerator;
+use bevy::{platform::collections::HashMap, prelude::*};
+
+/// `RandomStreams` hands out named random number generators, all
+/// derived from the seed of the master [`RandomNumberGenerator`].
+/// `RandomPlugin` inserts it as a resource.
+///
+/// Giving each subsystem its own stream (`"world"`, `"particles"`,
+/// `"ai"`) means that using random numbers in one never changes the
+/// numbers another receives, while a single seed still reproduces the
+/// whole run.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::{RandomNumberGenerator, RandomStreams};
+/// let mut streams = RandomStreams::new(&RandomNumberGenerator::seeded(1));
+/// let particle_roll = streams.get("particles").range(0..100);
+/// ```
+#[derive(Resource)]
+pub struct RandomStreams {
+  master: RandomNumberGenerator,
+  streams: HashMap<String, RandomNumberGenerator>,
+}
+
+impl RandomStreams {
+  /// Creates a set of streams derived from `master`'s seed, using the
+  /// same algorithm.
+  pub fn new(master: &RandomNumberGenerator) -> Self {
+    Self {
+      master: RandomNumberGenerator::with_algorithm(
+        master.algorithm(),
+        master.seed(),
+      ),
+      streams: HashMap::new(),
+    }
+  }
+
+  /// The seed every stream is derived from.
+  pub fn seed(&self) -> u64 {
+    self.master.seed()
+  }
+
+  /// Returns the named stream, creating it on first use.
+  pub fn get(&mut self, name: &str) -> &mut RandomNumberGenerator {
+    let master = &self.master;
+    self
+      .streams
+      .entry(name.to_string())
+      .or_insert_with(|| master.stream(name))
+  }
+}
@@ -0,0 +1,199 @@
+use crate::RandomNumberGenerator;
+
+/// `WeightedTable` picks items at random, with each item's chance of
+/// being picked proportional to its weight. Use it for loot drops,
+/// spawn mixes and similar "roll on a table" selections.
+///
+/// ## Example
+///
+/// ```
+/// use my_library_mbone_skeleton::{RandomNumberGenerator, WeightedTable};
+/// let loot = WeightedTable::new()
+///   .add("gold", 10)
+///   .add("potion", 5)
+///   .add("sword", 1);
+/// let mut rng = RandomNumberGenerator::seeded(1);
+/// let drop = loot.sample(&mut rng).unwrap();
+/// assert!(["gold", "potion", "sword"].contains(drop));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeightedTable<T> {
+  entries: Vec<(T, u32)>,
+  total_weight: u64,
+}
+
+impl<T> Default for WeightedTable<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T> WeightedTable<T> {
+  /// Creates an empty table.
+  pub fn new() -> Self {
+    Self {
+      entries: Vec::new(),
+      total_weight: 0,
+    }
+  }
+
+  /// Adds an item to the table. Items with a weight of zero are never
+  /// picked.
+  ///
+  /// # Arguments
+  ///
+  /// * `item` - the item to add.
+  /// * `weight` - the relative chance of `item` being picked.
+  pub fn add(mut self, item: T, weight: u32) -> Self {
+    self.entries.push((item, weight));
+    self.total_weight += weight as u64;
+    self
+  }
+
+  /// The number of entries in the table.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// `true` if the table has no entries.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The sum of every entry's weight.
+  pub fn total_weight(&self) -> u64 {
+    self.total_weight
+  }
+
+  /// Picks an item, leaving it in the table. Returns `None` if the
+  /// table is empty (or every weight is zero).
+  pub fn sample(&self, rng: &mut RandomNumberGenerator) -> Option<&T> {
+    self.sample_index(rng).map(|index| &self.entries[index].0)
+  }
+
+  /// Picks the index of an item, in the order items were added.
+  pub fn sample_index(
+    &self,
+    rng: &mut RandomNumberGenerator,
+  ) -> Option<usize> {
+    pick(
+      self.entries.iter().map(|(_, weight)| *weight),
+      self.total_weight,
+      rng,
+    )
+  }
+
+  /// Picks up to `count` distinct items without replacement. Each pick
+  /// is weighted against the items that haven't been picked yet.
+  pub fn sample_without_replacement(
+    &self,
+    rng: &mut RandomNumberGenerator,
+    count: usize,
+  ) -> Vec<&T> {
+    let mut weights: Vec<u32> =
+      self.entries.iter().map(|(_, weight)| *weight).collect();
+    let mut total_weight = self.total_weight;
+    let mut result = Vec::with_capacity(count.min(weights.len()));
+    while result.len() < count {
+      let Some(index) = pick(weights.iter().copied(), total_weight, rng)
+      else {
+        break;
+      };
+      total_weight -= weights[index] as u64;
+      weights[index] = 0;
+      result.push(&self.entries[index].0);
+    }
+    result
+  }
+
+  /// Picks an item and removes it from the table, so it can't be
+  /// picked again.
+  pub fn take(&mut self, rng: &mut RandomNumberGenerator) -> Option<T> {
+    let index = self.sample_index(rng)?;
+    let (item, weight) = self.entries.remove(index);
+    self.total_weight -= weight as u64;
+    Some(item)
+  }
+}
+
+fn pick(
+  weights: impl Iterator<Item = u32>,
+  total_weight: u64,
+  rng: &mut RandomNumberGenerator,
+) -> Option<usize> {
+  if total_weight == 0 {
+    return None;
+  }
+  let mut roll = rng.range(0..total_weight);
+  for (index, weight) in weights.enumerate() {
+    let weight = weight as u64;
+    if roll < weight {
+      return Some(index);
+    }
+    roll -= weight;
+  }
+  None
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn table() -> WeightedTable<&'static str> {
+    WeightedTable::new()
+      .add("common", 90)
+      .add("never", 0)
+      .add("rare", 10)
+  }
+
+  #[test]
+  fn test_empty() {
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let table: WeightedTable<u32> = WeightedTable::new();
+    assert!(table.sample(&mut rng).is_none());
+    assert!(table.sample_without_replacement(&mut rng, 3).is_empty());
+  }
+
+  #[test]
+  fn test_reproducibility() {
+    let table = table();
+    let mut rng = (
+      RandomNumberGenerator::seeded(1),
+      RandomNumberGenerator::seeded(1),
+    );
+    for _ in 0..1000 {
+      assert_eq!(table.sample(&mut rng.0), table.sample(&mut rng.1));
+    }
+  }
+
+  #[test]
+  fn test_weights() {
+    let table = table();
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let mut rare = 0;
+    for _ in 0..10_000 {
+      match *table.sample(&mut rng).unwrap() {
+        "rare" => rare += 1,
+        "never" => panic!("picked a zero-weight entry"),
+        _ => {}
+      }
+    }
+    assert!((800..1200).contains(&rare));
+  }
+
+  #[test]
+  fn test_without_replacement() {
+    let table = table();
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let picked = table.sample_without_replacement(&mut rng, 5);
+    assert_eq!(picked.len(), 2);
+    assert!(picked.contains(&&"common"));
+    assert!(picked.contains(&&"rare"));
+
+    let mut table = table;
+    assert!(table.take(&mut rng).is_some());
+    assert!(table.take(&mut rng).is_some());
+    assert!(table.take(&mut rng).is_none());
+    assert_eq!(table.len(), 1);
+  }
+}
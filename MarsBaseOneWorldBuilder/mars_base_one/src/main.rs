@@ -38,7 +38,7 @@ fn main() -> anyhow::Result<()> {
       }),
       ..default()
     }))
-    .add_plugins(RandomPlugin)
+    .add_plugins(RandomPlugin::new())
     .add_plugins(GameStatePlugin::new(
       GamePhase::MainMenu,
       GamePhase::Playing,
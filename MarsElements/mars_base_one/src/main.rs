@@ -66,7 +66,7 @@ fn main() -> anyhow::Result<()> {
         ..default()
       }))
       .add_plugins(FrameTimeDiagnosticsPlugin::default())
-      .add_plugins(RandomPlugin)
+      .add_plugins(RandomPlugin::new())
       .add_plugins(GameStatePlugin::new(
         GamePhase::MainMenu,
         GamePhase::WorldBuilding,
@@ -122,8 +122,7 @@ fn spawn_builder() {
 
     // Shuffle possible miner positions and limit the size to 20
     //START_HIGHLIGHT
-    use my_library::rand::seq::SliceRandom;
-    world.spawn_positions.shuffle(&mut rng.rng);
+    rng.shuffle(&mut world.spawn_positions);
     //END_HIGHLIGHT
     //END: WorldShuffle
 
@@ -0,0 +1,50 @@
+// With the `embedded_assets` feature, lists every file in the `assets`
+// directory for `embed_assets!` to compile into the game. Files the
+// manifest doesn't use are embedded too, so keep the directory tidy.
+use std::{fs, io, path::Path};
+
+fn main() -> io::Result<()> {
+  println!("cargo:rerun-if-changed=build.rs");
+  if std::env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_none() {
+    return Ok(());
+  }
+  println!("cargo:rerun-if-changed=assets");
+  let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+  let mut files = Vec::new();
+  list_files(&assets, &assets, &mut files)?;
+  files.sort();
+
+  let mut source = String::from("&[\n");
+  for filename in files {
+    let path = assets.join(&filename);
+    source.push_str(&format!(
+      "  ({filename:?}, include_bytes!({path:?}) as &[u8]),\n"
+    ));
+  }
+  source.push(']');
+  let out_dir = std::env::var("OUT_DIR").unwrap();
+  fs::write(Path::new(&out_dir).join("embedded_assets.rs"), source)
+}
+
+// Adds the files under `directory` to `files`, relative to `root` and
+// with `/` separators, as the asset manager names them.
+fn list_files(
+  root: &Path,
+  directory: &Path,
+  files: &mut Vec<String>,
+) -> io::Result<()> {
+  for entry in fs::read_dir(directory)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      println!("cargo:rerun-if-changed={}", path.display());
+      list_files(root, &path, files)?;
+    } else if let Ok(relative) = path.strip_prefix(root) {
+      let parts: Vec<_> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect();
+      files.push(parts.join("/"));
+    }
+  }
+  Ok(())
+}
@@ -27,10 +27,13 @@ struct Player {
   //START_HIGHLIGHT
   score: u32,
   //END_HIGHLIGHT
+  last_rescued: Option<String>,
 }
 //END: MBS_Player
 
 fn main() -> anyhow::Result<()> {
+  // With the embedded_assets feature, the game runs from any directory
+  embed_assets!();
   let mut app = App::new();
   add_phase!(app, GamePhase, GamePhase::WorldBuilding,
     start => [ spawn_builder ],
@@ -44,11 +47,14 @@ fn main() -> anyhow::Result<()> {
       apply_velocity, terminal_velocity.after(apply_velocity), 
       check_collisions::<Player, Ground>, bounce, 
       camera_follow.after(terminal_velocity),
-      show_performance, spawn_particle_system, particle_age_system,
+      spawn_particle_system, particle_age_system,
       miner_beacon,
-      score_display, check_collisions::<Player, Miner>,
+      check_collisions::<Player, Miner>,
       check_collisions::<Player, Fuel>, check_collisions::<Player, Battery>,
       collect_game_element_and_despawn::<Miner,{ BurstColor::Green as u8 }>,
+      name_rescued_miner.after(check_collisions::<Player, Miner>).before(
+        collect_game_element_and_despawn::<Miner,
+          { BurstColor::Green as u8 }>),
       collect_game_element_and_despawn::<Fuel, { BurstColor::Orange as u8 }>,
       collect_game_element_and_despawn::<Battery, 
         { BurstColor::Magenta as u8 }>
@@ -58,6 +64,9 @@ fn main() -> anyhow::Result<()> {
       //END_HIGHLIGHT
   );
   //END: ExitPhase
+  // Phase systems stop while the game is paused, but the HUD stays up
+  app.add_systems(Update, (show_performance, score_display)
+    .run_if(in_state(GamePhase::Playing)));
 
   //START: RegisterFinalScore
   app.add_event::<FinalScore>();
@@ -82,22 +91,20 @@ fn main() -> anyhow::Result<()> {
         ..default()
       }))
       .add_plugins(FrameTimeDiagnosticsPlugin::default())
-      .add_plugins(RandomPlugin)
+      .add_plugins(RandomPlugin::new())
       .add_plugins(GameStatePlugin::new(
         GamePhase::MainMenu,
         GamePhase::WorldBuilding,
         GamePhase::GameOver,
-      ))
+      ).with_pause(GamePhase::Playing, KeyCode::Escape, Menu::new()
+        .with_title("Paused")
+        .with_style(MenuStyle::Egui)
+        .with_button("Resume", None, MenuAction::Resume)
+        .with_button("Main Menu", KeyCode::KeyM,
+          MenuAction::GoTo(GamePhase::MainMenu))
+        .with_button("Quit", KeyCode::KeyQ, MenuAction::Quit)))
       .add_plugins(
-        AssetManager::new().add_image("ship", "ship.png")?
-            .add_image("ground", "ground.png")?
-            .add_image("backdrop", "backing.png")?
-            .add_image("particle", "particle.png")?
-            .add_image("mothership", "mothership.png")?
-            .add_image("spaceman", "spaceman.png")?
-            .add_image("fuel", "fuel.png")?
-            .add_image("battery", "battery.png")?
-
+        AssetManager::new().add_manifest("manifest.ron")?
       )
       .insert_resource(Animations::new())
       .add_event::<OnCollision<Player, Ground>>()
@@ -117,23 +124,24 @@ use bevy::render::camera::ScalingMode;
 
 static NEW_WORLD: Mutex<Option<World>> = Mutex::new(None);
 
-fn spawn_builder() {
+fn spawn_builder(mut streams: ResMut<RandomStreams>) {
   use std::sync::atomic::Ordering;
   // Clear the build state
   WORLD_READY.store(false, Ordering::Relaxed);
 
   // Spawn a "building world" message
 
+  // Fork a generator for this level from the world stream, so every
+  // level differs but the whole run is reproducible from one seed
+  let mut rng = streams.get("world").fork("level");
+
   //Start a world building thread
-  std::thread::spawn(|| {
-    // Make our own random number generator
-    let mut rng = my_library::RandomNumberGenerator::new();
+  std::thread::spawn(move || {
     // Spawn the world
     let mut world = World::new(200, 200, &mut rng);
 
-    // Shuffle possible miner positions and limit the size to 20
-    use my_library::rand::seq::SliceRandom;
-    world.spawn_positions.shuffle(&mut rng.rng);
+    // Place the miners and supplies
+    world.place_collectibles(&mut rng);
 
     // Store the world
     let mut lock = NEW_WORLD.lock().unwrap();
@@ -190,7 +198,10 @@ fn setup(
     &loaded_assets,
     GameElement,
     //START_HIGHLIGHT
-    Player { miners_saved: 0, shields: 500, fuel: 100_000, score: 0 },
+    Player {
+      miners_saved: 0, shields: 500, fuel: 100_000, score: 0,
+      last_rescued: None,
+    },
     //END_HIGHLIGHT
     Velocity::default(),
     PhysicsPosition::new(Vec2::new(0.0, 200.0)),
@@ -298,7 +309,7 @@ fn end_game(
   let Ok(player) = player_query.single() else {
     return;
   };
-  if player.miners_saved == 20 {
+  if player.miners_saved == MINERS as u32 {
     // You won!
     state.set(GamePhase::GameOver);
   }
@@ -324,6 +335,7 @@ fn bounce(
   mut impulses: EventWriter<Impulse>,
   mut particles: EventWriter<SpawnParticle>,
   mut state: ResMut<NextState<GamePhase>>,
+  mut streams: ResMut<RandomStreams>,
 ) {
   let mut bounce = Vec2::default();
   let mut entity = None;
@@ -357,6 +369,7 @@ fn bounce(
       LinearRgba::new(0.0, 0.0, 1.0, 1.0),
       &mut particles,
       3.0,
+      streams.get("particles"),
     );
     player.shields -= 1;
     if player.shields <= 0 {
@@ -378,22 +391,57 @@ struct World {
   mesh: Option<Mesh>,
   tile_positions: Vec<(f32, f32)>,
   spawn_positions: Vec<(f32, f32)>,
+  collectibles: Vec<(Collectible, (f32, f32))>,
+}
+
+#[derive(Clone)]
+enum Collectible {
+  Miner(String),
+  Fuel,
+  Battery,
 }
 
+// Every level has 20 miners to rescue
+const MINERS: usize = 20;
+// The number of fuel and battery pickups
+const SUPPLIES: usize = 30;
+// The closest any two collectibles can be (6 tiles)
+const COLLECTIBLE_SPACING: f32 = 6.0 * 24.0;
+// Miners' names are invented from these
+const MINER_NAMES: [&str; 24] = [
+  "amelia", "anders", "beatriz", "bruno", "chandra", "dmitri", "elena",
+  "farida", "gustav", "hana", "ingrid", "jomo", "kenji", "leona", "mateo",
+  "nadia", "oskar", "priya", "rosalind", "sergei", "tamsin", "ulrich",
+  "valentina", "yusuf",
+];
+
 impl World {
   fn mapidx(&self, x: usize, y: usize) -> usize {
     y * self.width + x
   }
 
-  fn find_random_closed_tile(&self, rng: &mut RandomNumberGenerator) -> (usize, usize) {
-    loop {
-      let x = rng.range(0 .. self.width);
-      let y = rng.range(0 .. self.height);
-      let idx = self.mapidx(x, y);
-      if self.solid[idx] {
-        return (x, y);
+  fn fill_unreachable(&mut self, start_x: usize, start_y: usize) {
+    let mut reachable = vec![false; self.solid.len()];
+    let mut open_list = vec![(start_x, start_y)];
+    reachable[self.mapidx(start_x, start_y)] = true;
+    while let Some((x, y)) = open_list.pop() {
+      let neighbors = [
+        (x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1),
+      ];
+      for (nx, ny) in neighbors {
+        if nx >= self.width || ny >= self.height {
+          continue;
+        }
+        let idx = self.mapidx(nx, ny);
+        if !self.solid[idx] && !reachable[idx] {
+          reachable[idx] = true;
+          open_list.push((nx, ny));
+        }
       }
     }
+    self.solid.iter_mut().zip(reachable).for_each(|(solid, reachable)| {
+      *solid |= !reachable;
+    });
   }
 
   fn clear_tiles(&mut self, x: usize, y: usize) {
@@ -437,6 +485,7 @@ impl World {
       mesh: None,
       tile_positions: Vec::new(),
       spawn_positions: Vec::new(),
+      collectibles: Vec::new(),
     };
 
     // Set the center tile and surrounding tiles to be empty
@@ -467,35 +516,31 @@ impl World {
       result.clear_tiles(width / 2, y);
     }
 
-    // Outward diffusion
-    let mut done = false;
-    while !done {
-      let start_tile = holes[rng.range(0..10)];
-      let target = result.find_random_closed_tile(rng);
-      let (mut x, mut y) = (start_tile.0 as f32, start_tile.1 as f32);
-      let (slope_x, slope_y) = (
-        (target.0 as f32 - x) / width as f32,
-        (target.1 as f32 - y) / height as f32,
-      );
-
-      loop {
-        if x < 1.0 || x > width as f32 || y < 1.0 || y > height as f32 {
-          break;
+    // Carve organic caves wherever domain-warped noise is high, opening
+    // up 30% of the map
+    let noise = Noise::new(rng);
+    let fractal = Fractal { octaves: 3, frequency: 0.05, ..default() };
+    let values: Vec<f32> = (0 .. width * height)
+      .map(|idx| {
+        let (x, y) = ((idx % width) as f32, (idx / width) as f32);
+        noise.warp2(NoiseType::OpenSimplex, x, y, &fractal, 12.0)
+      })
+      .collect();
+    let mut sorted = values.clone();
+    sorted.sort_by(f32::total_cmp);
+    let threshold = sorted[sorted.len() * 7 / 10];
+    for y in 1 .. height-1 {
+      for x in 1 .. width-1 {
+        let idx = result.mapidx(x, y);
+        if values[idx] > threshold {
+          result.solid[idx] = false;
         }
-        let tile_id = result.mapidx(x as usize, y as usize);
-        if result.solid[tile_id] {
-          result.clear_tiles(x as usize, y as usize);
-          break;
-        }
-        x += slope_x;
-        y += slope_y;
       }
-
-      let solid_count = result.solid.iter().filter(|s| **s).count();
-      let solid_percent = solid_count as f32 / (width * height) as f32;
-      if solid_percent < 0.7 { done = true; }
     }
 
+    // Fill in any caves the player can't reach from the borehole
+    result.fill_unreachable(width / 2, height / 2);
+
     let (mesh, tile_positions, possible_miner_positions) = result.build_mesh();
     result.mesh = Some(mesh);
     result.tile_positions = tile_positions;
@@ -504,6 +549,40 @@ impl World {
     result
   }
 
+  fn place_collectibles(&mut self, rng: &mut RandomNumberGenerator) {
+    let supplies = WeightedTable::new()
+      .add(Collectible::Fuel, 2)
+      .add(Collectible::Battery, 1);
+    // Spread collectibles evenly through the caves. Small caves might not
+    // have room for all of them, so make up the shortfall from the open
+    // positions that weren't chosen.
+    let mut candidates: Vec<Vec2> = self.spawn_positions.iter()
+      .map(|(x, y)| Vec2::new(*x, *y))
+      .collect();
+    let mut positions = PoissonDisk::new(COLLECTIBLE_SPACING)
+      .select(rng, &candidates);
+    let shortfall = (MINERS + SUPPLIES).saturating_sub(positions.len());
+    if shortfall > 0 {
+      candidates.retain(|pos| !positions.contains(pos));
+      rng.shuffle(&mut candidates);
+      positions.extend(candidates.into_iter().take(shortfall));
+    }
+    let mut names = NameGenerator::new(2)
+      .train(MINER_NAMES)
+      .with_length(4, 9)
+      .generate_many(rng, MINERS)
+      .into_iter();
+    let mut positions = positions.into_iter().map(|pos| (pos.x, pos.y));
+    for (i, pos) in positions.by_ref().take(MINERS).enumerate() {
+      let name = names.next().unwrap_or_else(|| format!("Miner {}", i + 1));
+      self.collectibles.push((Collectible::Miner(name), pos));
+    }
+    for pos in positions.take(SUPPLIES) {
+      let kind = supplies.sample(rng).unwrap().clone();
+      self.collectibles.push((kind, pos));
+    }
+  }
+
   fn build_mesh(&self) -> (Mesh, Vec<(f32, f32)>, Vec<(f32, f32)>) {
     let mut position = Vec::new();
     let mut uv = Vec::new();
@@ -602,65 +681,49 @@ impl World {
         .insert(AxisAlignedBoundingBox::new(24.0, 24.0));
     }
 
-    // Spawn miners
-    for (x, y) in self.spawn_positions.iter().take(20) {
-      spawn_image!(
-        assets,
-        commands,
-        "spaceman",
-        *x,
-        *y,
-        10.0,
-        loaded_assets,
-        GameElement,
-        Miner,
-        Velocity::default(),
-        PhysicsPosition::new(Vec2::new(*x, *y)),
-        // Extra Large Hitbox
-        AxisAlignedBoundingBox::new(48.0, 48.0)
-      );
-    }
-
-    // Spawn fuel
-    for (x, y) in self.spawn_positions.iter().skip(20).take(20) {
-      spawn_image!(
-        assets,
-        commands,
-        "fuel",
-        *x,
-        *y,
-        10.0,
-        loaded_assets,
-        GameElement,
-        Fuel,
-        Velocity::default(),
-        PhysicsPosition::new(Vec2::new(*x, *y)),
-        // Extra Large Hitbox
-        AxisAlignedBoundingBox::new(48.0, 48.0)
-      );
-    }
-
-    // Spawn batteries
-    for (x, y) in self.spawn_positions.iter().skip(40).take(10) {
-      spawn_image!(
-        assets,
-        commands,
-        "battery",
-        *x,
-        *y,
-        10.0,
-        loaded_assets,
-        GameElement,
-        Battery,
-        Velocity::default(),
-        PhysicsPosition::new(Vec2::new(*x, *y)),
-        // Extra Large Hitbox
-        AxisAlignedBoundingBox::new(48.0, 48.0)
-      );
+    for (kind, position) in self.collectibles.iter() {
+      let position = *position;
+      match kind {
+        Collectible::Miner(name) => spawn_collectible(
+          assets, commands, loaded_assets, "spaceman", position,
+          Miner { name: name.clone() }
+        ),
+        Collectible::Fuel => spawn_collectible(
+          assets, commands, loaded_assets, "fuel", position, Fuel
+        ),
+        Collectible::Battery => spawn_collectible(
+          assets, commands, loaded_assets, "battery", position, Battery
+        ),
+      }
     }
   }
 }
 
+fn spawn_collectible<T: Component>(
+  assets: &AssetStore,
+  commands: &mut Commands,
+  loaded_assets: &LoadedAssets,
+  tag: &str,
+  (x, y): (f32, f32),
+  collectible: T,
+) {
+  spawn_image!(
+    assets,
+    commands,
+    tag,
+    x,
+    y,
+    10.0,
+    loaded_assets,
+    GameElement,
+    collectible,
+    Velocity::default(),
+    PhysicsPosition::new(Vec2::new(x, y)),
+    // Extra Large Hitbox
+    AxisAlignedBoundingBox::new(48.0, 48.0)
+  );
+}
+
 fn show_performance(
   diagnostics: Res<DiagnosticsStore>,
   mut egui_context: egui::EguiContexts,
@@ -801,6 +864,7 @@ fn collect_game_element_and_despawn<T:Component + OnCollect, const COLOR: u8>
   mut commands: Commands,
   mut player: Query<(&mut Player, &Transform)>,
   mut spawn: EventWriter<SpawnParticle>,
+  mut streams: ResMut<RandomStreams>,
 )
  {
   let mut collected = Vec::new();
@@ -826,22 +890,40 @@ fn collect_game_element_and_despawn<T:Component + OnCollect, const COLOR: u8>
       BurstColor::from(COLOR).into(),
       &mut spawn,
       2.0,
+      streams.get("particles"),
     );
   }
 }
 
+fn name_rescued_miner(
+  mut collisions: EventReader<OnCollision<Player, Miner>>,
+  miners: Query<&Miner>,
+  mut player: Query<&mut Player>,
+) {
+  let Ok(mut player) = player.single_mut() else {
+    return;
+  };
+  for collision in collisions.read() {
+    if let Ok(miner) = miners.get(collision.entity_b) {
+      player.last_rescued = Some(miner.name.clone());
+    }
+  }
+}
+
 fn miner_beacon(
-  mut rng: ResMut<RandomNumberGenerator>,
+  mut streams: ResMut<RandomStreams>,
   miners: Query<&Transform, With<Miner>>,
   mut spawn: EventWriter<SpawnParticle>, 
 ) {
+  let rng = streams.get("particles");
   for miner in miners.iter() {
-    if rng.range(1 ..= 100) == 100 {
+    if rng.chance(0.01) {
       particle_burst(
         miner.translation.truncate(),
         LinearRgba::new(1.0, 1.0, 0.0, 1.0),
         &mut spawn, 
-        10.0)
+        10.0,
+        rng)
     }
   }
 }
@@ -863,6 +945,9 @@ fn score_display(
       ui.label(format!("Miners Saved: {}", player.miners_saved));
       ui.label(format!("Shields: {}", player.shields));
       ui.label(format!("Fuel: {}", player.fuel));
+      if let Some(name) = &player.last_rescued {
+        ui.label(format!("Last rescued: {name}"));
+      }
     });
 }
 //END: DisplayScore
@@ -872,20 +957,24 @@ fn particle_burst(
   color: LinearRgba,
   spawn: &mut EventWriter<SpawnParticle>,
   velocity: f32,
+  rng: &mut RandomNumberGenerator,
 ) {
-  for angle in 0 .. 360 {
-    let angle = (angle as f32).to_radians();
-    let velocity = Vec3::new(angle.cos() * velocity, angle.sin() * velocity, 0.0);
+  // Spray particles in random directions and at varied speeds, rather
+  // than as a perfect ring
+  for _ in 0 .. 360 {
+    let velocity = rng.point_on_ring(Vec2::ZERO, velocity * 0.25, velocity);
     spawn.write(SpawnParticle {
       position: center,
       color,
-      velocity,
+      velocity: velocity.extend(0.0),
     });
   }
 }
 
 #[derive(Component)]
-struct Miner;
+struct Miner {
+  name: String,
+}
 
 #[derive(Component)]
 struct Battery;